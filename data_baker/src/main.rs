@@ -37,7 +37,7 @@ fn generate_dummy_data(file_path: &str) -> std::io::Result<()> {
         let local_epoch_seconds = local_time.timestamp();
 
         let record = format!(
-            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{:.2},{},{},{},{:.2},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{:.2},{},{},{},{:.2},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{:.2},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
             epoch_seconds,                                     // orderdate_epoch
             local_epoch_seconds,                              // ordertime_epoch
             random_string(50),                                // accountnumber
@@ -0,0 +1,134 @@
+//! Integer column encodings against raw 8-byte storage, on the output of
+//! `data_baker` (`cargo run -p data_baker` regenerates it):
+//!
+//! ```text
+//! cargo bench -p yohsin --bench integer_columns
+//! ```
+//!
+//! The encoded size of each column is printed before the timings.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use yohsin::integer;
+use yohsin::order_struct::DailyBlotterData;
+
+const DATA_FILE: &str = "../data_baker/data/data-file.csv";
+
+/// The integer columns of the data file, by name.
+fn columns() -> Vec<(&'static str, Vec<i64>)> {
+    let data = DailyBlotterData::load_from_file(DATA_FILE).expect("data_baker output");
+    let column = |value: fn(&DailyBlotterData) -> i64| data.iter().map(value).collect();
+    vec![
+        ("orderdate", column(|r| r.orderdate)),
+        ("ordertime", column(|r| r.ordertime)),
+        ("created_date", column(|r| r.created_date)),
+        ("id", column(|r| r.id)),
+        ("qty", column(|r| r.qty)),
+        ("cumqty", column(|r| r.cumqty as i64)),
+        ("qtyleaves", column(|r| r.qtyleaves as i64)),
+    ]
+}
+
+fn encode_raw(buf: &mut Vec<u8>, values: &[i64]) {
+    for value in values {
+        buf.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+fn decode_raw(bytes: &[u8]) -> Vec<i64> {
+    bytes
+        .chunks_exact(8)
+        .map(|value| i64::from_le_bytes(value.try_into().unwrap()))
+        .collect()
+}
+
+fn integer_columns(c: &mut Criterion) {
+    let columns = columns();
+    for (name, values) in &columns {
+        let (mut raw, mut delta, mut frame) = (Vec::new(), Vec::new(), Vec::new());
+        encode_raw(&mut raw, values);
+        integer::encode_delta(&mut delta, values);
+        integer::encode_frame_of_reference(&mut frame, values);
+        println!(
+            "{:<12} {} values: raw {} bytes, delta {} bytes, frame of reference {} bytes",
+            name,
+            values.len(),
+            raw.len(),
+            delta.len(),
+            frame.len()
+        );
+    }
+
+    let mut group = c.benchmark_group("encode");
+    group.bench_function("raw", |b| {
+        b.iter(|| {
+            let mut buf = Vec::new();
+            for (_, values) in &columns {
+                encode_raw(&mut buf, black_box(values));
+            }
+            buf
+        })
+    });
+    group.bench_function("delta", |b| {
+        b.iter(|| {
+            let mut buf = Vec::new();
+            for (_, values) in &columns {
+                integer::encode_delta(&mut buf, black_box(values));
+            }
+            buf
+        })
+    });
+    group.bench_function("frame_of_reference", |b| {
+        b.iter(|| {
+            let mut buf = Vec::new();
+            for (_, values) in &columns {
+                integer::encode_frame_of_reference(&mut buf, black_box(values));
+            }
+            buf
+        })
+    });
+    group.finish();
+
+    let encoded = |encode: fn(&mut Vec<u8>, &[i64])| -> Vec<(usize, Vec<u8>)> {
+        columns
+            .iter()
+            .map(|(_, values)| {
+                let mut buf = Vec::new();
+                encode(&mut buf, values);
+                (values.len(), buf)
+            })
+            .collect()
+    };
+    let raw = encoded(encode_raw);
+    let delta = encoded(integer::encode_delta);
+    let frame = encoded(integer::encode_frame_of_reference);
+    let mut group = c.benchmark_group("decode");
+    group.bench_function("raw", |b| {
+        b.iter(|| {
+            raw.iter()
+                .map(|(_, bytes)| decode_raw(black_box(bytes)))
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("delta", |b| {
+        b.iter(|| {
+            delta
+                .iter()
+                .map(|(count, bytes)| integer::decode_delta(black_box(bytes), *count).unwrap())
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("frame_of_reference", |b| {
+        b.iter(|| {
+            frame
+                .iter()
+                .map(|(count, bytes)| {
+                    integer::decode_frame_of_reference(black_box(bytes), *count).unwrap()
+                })
+                .collect::<Vec<_>>()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, integer_columns);
+criterion_main!(benches);
@@ -0,0 +1,351 @@
+//! Records are stored in chunks of consecutive records, located through a
+//! chunk table at the end of the file.
+//!
+//! ```text
+//! chunk        record_count u32, then one u32 end offset per record
+//!              (relative to the first record), then the encoded records
+//! chunk table  chunk_count u32, then per chunk:
+//!              offset u64, len u64, first_record u64, record_count u32,
+//!              crc u32 (CRC32C of the chunk bytes),
+//!              then table_crc u32
+//! ```
+//!
+//! `table_crc` is the CRC32C of the file header followed by the chunk table
+//! up to it. Since the table holds the checksum of every chunk, it covers the
+//! whole file.
+//!
+//! Chunking only depends on the data and on the number of records per chunk,
+//! never on how many threads wrote the file. Files with the
+//! [`Layout::Columns`] layout store each chunk as a row group instead, see
+//! [`crate::columnar`]. Chunks may be compressed, see [`crate::compression`]:
+//! offsets, lengths and checksums in the table are those of the stored bytes.
+//! In a compressed file, a chunk in the row layout starts with a flags byte,
+//! [`STORED`] if the rest of the chunk is not compressed.
+
+use crate::codec::ByteReader;
+use crate::columnar::{encode_row_group, row_group_to_rows, ROW_GROUP_PREFIX_LEN};
+use crate::compression::{Compression, STORED};
+use crate::error::{Error, Result};
+use crate::header::FileHeader;
+use crate::record::Record;
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Number of records per chunk unless configured otherwise.
+pub const DEFAULT_CHUNK_RECORDS: usize = 4096;
+
+const ENTRY_LEN: usize = 32;
+
+/// How the records of a chunk are laid out, recorded in the file header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Layout {
+    /// One record after the other.
+    #[default]
+    Rows,
+    /// One column per field.
+    Columns,
+}
+
+impl Layout {
+    /// Stable identifier of the layout in file headers.
+    pub const fn tag(self) -> u8 {
+        match self {
+            Layout::Rows => 0,
+            Layout::Columns => 1,
+        }
+    }
+
+    pub const fn from_tag(tag: u8) -> Option<Layout> {
+        match tag {
+            0 => Some(Layout::Rows),
+            1 => Some(Layout::Columns),
+            _ => None,
+        }
+    }
+}
+
+/// Encodes `records` as one chunk laid out as `layout` and compressed with
+/// `compression`.
+pub fn encode_chunk_as<T: Record>(
+    records: &[T],
+    layout: Layout,
+    compression: Compression,
+) -> Vec<u8> {
+    match layout {
+        Layout::Rows if compression == Compression::None => encode_chunk(records),
+        Layout::Rows => {
+            let (stored, bytes) = compression.compress_or_store(encode_chunk(records));
+            let mut chunk = Vec::with_capacity(1 + bytes.len());
+            chunk.push(if stored { STORED } else { 0 });
+            chunk.extend_from_slice(&bytes);
+            chunk
+        }
+        Layout::Columns => encode_row_group(records, compression),
+    }
+}
+
+/// Returns chunk number `idx`, read from the file whose header is `header`,
+/// decompressed and in the row layout the other functions of this module
+/// work on. A chunk stored in the row layout is borrowed, not copied.
+pub fn chunk_as_rows<'a>(
+    header: &FileHeader,
+    idx: usize,
+    chunk: &'a [u8],
+) -> Result<Cow<'a, [u8]>> {
+    match header.layout {
+        Layout::Rows if header.compression == Compression::None => Ok(Cow::Borrowed(chunk)),
+        Layout::Rows => {
+            let (&flags, bytes) = chunk
+                .split_first()
+                .ok_or(Error::Truncated { what: "chunk" })?;
+            match flags {
+                0 => header
+                    .compression
+                    .decompress(Cow::Borrowed(bytes), &format!("chunk {}", idx)),
+                STORED => Ok(Cow::Borrowed(bytes)),
+                _ => Err(Error::Corrupt(format!("Invalid flags of chunk {}", idx))),
+            }
+        }
+        Layout::Columns => {
+            let types: Vec<_> = header.fields.iter().map(|(_, ty)| *ty).collect();
+            row_group_to_rows(chunk, &types, header.compression).map(Cow::Owned)
+        }
+    }
+}
+
+/// [`chunk_as_rows`] of a chunk read into its own buffer, which is returned
+/// as is if already in the row layout.
+pub fn chunk_into_rows(header: &FileHeader, idx: usize, chunk: Vec<u8>) -> Result<Vec<u8>> {
+    if header.layout == Layout::Rows && header.compression == Compression::None {
+        return Ok(chunk);
+    }
+    chunk_as_rows(header, idx, &chunk).map(Cow::into_owned)
+}
+
+/// Encodes `records` as one chunk.
+pub fn encode_chunk<T: Record>(records: &[T]) -> Vec<u8> {
+    let mut body = Vec::new();
+    let mut ends = Vec::with_capacity(records.len());
+    for record in records {
+        record.encode(&mut body);
+        ends.push(body.len() as u32);
+    }
+
+    let mut chunk = Vec::with_capacity(4 + 4 * ends.len() + body.len());
+    chunk.extend_from_slice(&(records.len() as u32).to_le_bytes());
+    for end in ends {
+        chunk.extend_from_slice(&end.to_le_bytes());
+    }
+    chunk.extend_from_slice(&body);
+    chunk
+}
+
+/// Returns the bytes of each record of an encoded chunk.
+pub fn chunk_records(chunk: &[u8]) -> Result<Vec<&[u8]>> {
+    let mut r = ByteReader::new(chunk);
+    let count = r.u32().ok_or(Error::Truncated { what: "chunk" })? as usize;
+    // Every record takes at least its end offset
+    let mut ends = Vec::with_capacity(count.min(chunk.len() / 4));
+    for _ in 0..count {
+        ends.push(r.u32().ok_or(Error::Truncated { what: "chunk" })? as usize);
+    }
+    let body = &chunk[r.position()..];
+
+    let mut records = Vec::with_capacity(ends.len());
+    let mut start = 0;
+    for end in ends {
+        let bytes = body.get(start..end).ok_or_else(|| {
+            Error::Corrupt(format!(
+                "Invalid offset: {}..{} (binary data length: {})",
+                start,
+                end,
+                body.len()
+            ))
+        })?;
+        records.push(bytes);
+        start = end;
+    }
+    Ok(records)
+}
+
+/// Returns the bytes of record number `idx` (relative to the chunk) of an
+/// encoded chunk, without reading the other records.
+pub fn chunk_record(chunk: &[u8], idx: usize) -> Result<&[u8]> {
+    let mut r = ByteReader::new(chunk);
+    let count = r.u32().ok_or(Error::Truncated { what: "chunk" })? as usize;
+    if idx >= count {
+        return Err(Error::Corrupt(format!("Chunk holds no record {}", idx)));
+    }
+    let end_at = |i: usize| {
+        let pos = 4 + 4 * i;
+        chunk
+            .get(pos..pos + 4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()) as usize)
+            .ok_or(Error::Truncated { what: "chunk" })
+    };
+    let start = if idx == 0 { 0 } else { end_at(idx - 1)? };
+    let end = end_at(idx)?;
+    let body = &chunk[(4 + 4 * count).min(chunk.len())..];
+    body.get(start..end).ok_or_else(|| {
+        Error::Corrupt(format!(
+            "Invalid offset: {}..{} (binary data length: {})",
+            start,
+            end,
+            body.len()
+        ))
+    })
+}
+
+/// Decodes the records at positions `range` (relative to the chunk) of an
+/// encoded chunk.
+pub fn decode_chunk<T: Record>(chunk: &[u8], range: Range<usize>) -> Result<Vec<T>> {
+    let records = chunk_records(chunk)?;
+    let bytes = records
+        .get(range.clone())
+        .ok_or_else(|| Error::Corrupt(format!("Chunk holds no records {:?}", range)))?;
+    bytes.iter().map(|bytes| T::decode(bytes)).collect()
+}
+
+/// Location of one chunk in the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkEntry {
+    pub offset: u64,
+    pub len: u64,
+    pub first_record: u64,
+    pub record_count: u32,
+    pub crc: u32,
+}
+
+impl ChunkEntry {
+    pub fn records(&self) -> Range<u64> {
+        self.first_record..self.first_record + self.record_count as u64
+    }
+
+    /// Checks the bytes read for chunk number `idx` against its checksum.
+    pub fn verify(&self, idx: usize, bytes: &[u8]) -> Result<()> {
+        let found = crc32c::crc32c(bytes);
+        if found != self.crc {
+            return Err(Error::ChecksumMismatch {
+                chunk: idx,
+                records: self.records(),
+                expected: self.crc,
+                found,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChunkTable {
+    pub entries: Vec<ChunkEntry>,
+}
+
+impl ChunkTable {
+    /// Encodes the table of a file whose header is `header`.
+    pub fn encode(&self, header: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(Self::encoded_len(self.entries.len()));
+        buf.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        for entry in &self.entries {
+            buf.extend_from_slice(&entry.offset.to_le_bytes());
+            buf.extend_from_slice(&entry.len.to_le_bytes());
+            buf.extend_from_slice(&entry.first_record.to_le_bytes());
+            buf.extend_from_slice(&entry.record_count.to_le_bytes());
+            buf.extend_from_slice(&entry.crc.to_le_bytes());
+        }
+        let crc = crc32c::crc32c_append(crc32c::crc32c(header), &buf);
+        buf.extend_from_slice(&crc.to_le_bytes());
+        buf
+    }
+
+    pub fn encoded_len(chunk_count: usize) -> usize {
+        4 + chunk_count * ENTRY_LEN + 4
+    }
+
+    /// Decodes the table of a file whose header is `header`, checking that
+    /// neither of them is damaged.
+    pub fn decode(bytes: &[u8], header: &[u8]) -> Result<Self> {
+        let mut r = ByteReader::new(bytes);
+        let count = r.u32().ok_or(Error::Truncated {
+            what: "chunk table",
+        })? as usize;
+        let mut entries = Vec::with_capacity(count.min(bytes.len() / ENTRY_LEN));
+        for _ in 0..count {
+            let (Some(offset), Some(len), Some(first_record), Some(record_count), Some(crc)) =
+                (r.u64(), r.u64(), r.u64(), r.u32(), r.u32())
+            else {
+                return Err(Error::Truncated {
+                    what: "chunk table",
+                });
+            };
+            entries.push(ChunkEntry {
+                offset,
+                len,
+                first_record,
+                record_count,
+                crc,
+            });
+        }
+
+        let covered = r.position();
+        let expected = r.u32().ok_or(Error::Truncated {
+            what: "chunk table",
+        })?;
+        let found = crc32c::crc32c_append(crc32c::crc32c(header), &bytes[..covered]);
+        if found != expected {
+            return Err(Error::ChunkTableChecksumMismatch { expected, found });
+        }
+        Ok(ChunkTable { entries })
+    }
+
+    /// Checks that the table matches `header` and that its chunks lie between
+    /// the header (`header_len` bytes) and the table, in record order, each
+    /// large enough for its record offsets (or its row group prefix, or its
+    /// flags byte if compressed).
+    pub fn check_layout(&self, header: &FileHeader, header_len: usize) -> Result<()> {
+        if self.record_count() != header.record_count {
+            return Err(Error::Corrupt(format!(
+                "Chunk table holds {} records, header announces {}",
+                self.record_count(),
+                header.record_count
+            )));
+        }
+
+        let mut next_record = 0;
+        for (idx, entry) in self.entries.iter().enumerate() {
+            let min_len = match (header.layout, header.compression) {
+                (Layout::Rows, Compression::None) => 4 + 4 * entry.record_count as u64,
+                (Layout::Rows, _) => 1,
+                (Layout::Columns, _) => ROW_GROUP_PREFIX_LEN as u64,
+            };
+            let in_bounds = entry.offset >= header_len as u64
+                && entry
+                    .offset
+                    .checked_add(entry.len)
+                    .is_some_and(|end| end <= header.chunk_table_offset);
+            if !in_bounds || entry.first_record != next_record || entry.len < min_len {
+                return Err(Error::Corrupt(format!("Invalid chunk table entry {}", idx)));
+            }
+            next_record = entry.records().end;
+        }
+        Ok(())
+    }
+
+    pub fn record_count(&self) -> u64 {
+        self.entries.last().map_or(0, |entry| entry.records().end)
+    }
+
+    /// Indices of the chunks holding the records in `range`.
+    pub fn chunks_for(&self, range: &Range<u64>) -> Range<usize> {
+        if range.start >= range.end {
+            return 0..0;
+        }
+        let first = self
+            .entries
+            .partition_point(|entry| entry.records().end <= range.start);
+        let last = self
+            .entries
+            .partition_point(|entry| entry.first_record < range.end);
+        first..last.max(first)
+    }
+}
@@ -0,0 +1,196 @@
+//! Byte-level layout of a single record on disk.
+//!
+//! A record is written as a fixed-size slot area followed by a variable-length
+//! tail. Scalars live directly in their slot; a string slot holds the
+//! `(offset, len)` of its bytes inside the tail, both as little-endian `u32`
+//! relative to the start of the record. Nothing in the encoding depends on the
+//! address space of the writer, so a file produced by one process can be read
+//! by any other.
+
+use crate::error::{Error, Result};
+use crate::record::FieldType;
+
+/// Size of the slot taken by a string: a `u32` offset and a `u32` length.
+pub const STR_SLOT: usize = 8;
+
+/// Initial state of [`fnv1a`].
+pub const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// Folds `bytes` into an FNV-1a 64 hash, starting from [`FNV_OFFSET`].
+pub fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// Writes one record into a buffer, slot by slot.
+pub struct RowWriter<'a> {
+    buf: &'a mut Vec<u8>,
+    start: usize,
+    slot: usize,
+}
+
+impl<'a> RowWriter<'a> {
+    /// Reserves `fixed_len` bytes of slots at the end of `buf`.
+    pub fn new(buf: &'a mut Vec<u8>, fixed_len: usize) -> Self {
+        let start = buf.len();
+        buf.resize(start + fixed_len, 0);
+        RowWriter {
+            buf,
+            start,
+            slot: start,
+        }
+    }
+
+    fn put_slot(&mut self, bytes: &[u8]) {
+        self.buf[self.slot..self.slot + bytes.len()].copy_from_slice(bytes);
+        self.slot += bytes.len();
+    }
+
+    pub fn put_i32(&mut self, v: i32) {
+        self.put_slot(&v.to_le_bytes());
+    }
+
+    pub fn put_i64(&mut self, v: i64) {
+        self.put_slot(&v.to_le_bytes());
+    }
+
+    pub fn put_f64(&mut self, v: f64) {
+        self.put_slot(&v.to_le_bytes());
+    }
+
+    pub fn put_bool(&mut self, v: bool) {
+        self.put_slot(&[v as u8]);
+    }
+
+    pub fn put_str(&mut self, v: &str) {
+        self.put_str_bytes(v.as_bytes());
+    }
+
+    fn put_str_bytes(&mut self, v: &[u8]) {
+        let offset = (self.buf.len() - self.start) as u32;
+        self.buf.extend_from_slice(v);
+        self.put_slot(&offset.to_le_bytes());
+        self.put_slot(&(v.len() as u32).to_le_bytes());
+    }
+
+    /// Writes a field of type `ty` from its encoded bytes: what its slot
+    /// holds for a scalar, the bytes of a string. Strings are checked when
+    /// the record is decoded.
+    pub fn put_encoded(&mut self, ty: FieldType, value: &[u8]) {
+        match ty {
+            FieldType::Str => self.put_str_bytes(value),
+            _ => self.put_slot(value),
+        }
+    }
+}
+
+/// Reads one record back, slot by slot, in the order it was written.
+pub struct RowReader<'a> {
+    bytes: &'a [u8],
+    slot: usize,
+}
+
+impl<'a> RowReader<'a> {
+    pub fn new(bytes: &'a [u8], fixed_len: usize) -> Result<Self> {
+        if bytes.len() < fixed_len {
+            return Err(Error::Corrupt(format!(
+                "Record too short: {} bytes (expected at least {})",
+                bytes.len(),
+                fixed_len
+            )));
+        }
+        Ok(RowReader { bytes, slot: 0 })
+    }
+
+    fn take<const N: usize>(&mut self) -> [u8; N] {
+        let mut out = [0u8; N];
+        out.copy_from_slice(&self.bytes[self.slot..self.slot + N]);
+        self.slot += N;
+        out
+    }
+
+    pub fn get_i32(&mut self) -> i32 {
+        i32::from_le_bytes(self.take())
+    }
+
+    pub fn get_i64(&mut self) -> i64 {
+        i64::from_le_bytes(self.take())
+    }
+
+    pub fn get_f64(&mut self) -> f64 {
+        f64::from_le_bytes(self.take())
+    }
+
+    pub fn get_bool(&mut self) -> bool {
+        self.take::<1>()[0] != 0
+    }
+
+    pub fn get_str(&mut self) -> Result<&'a str> {
+        let offset = u32::from_le_bytes(self.take()) as usize;
+        let len = u32::from_le_bytes(self.take()) as usize;
+        let bytes = self
+            .bytes
+            .get(offset..offset + len)
+            .ok_or_else(|| Error::Corrupt("String slot points outside of the record".into()))?;
+        std::str::from_utf8(bytes).map_err(|e| Error::Corrupt(format!("Invalid string: {}", e)))
+    }
+}
+
+/// Bounds-checked sequential reader over little-endian values, used for file
+/// metadata. Every getter returns `None` once the input is exhausted.
+pub struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        ByteReader { bytes, pos: 0 }
+    }
+
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let out = self.bytes.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(out)
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.bytes(N).map(|b| b.try_into().unwrap())
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        self.array::<1>().map(|b| b[0])
+    }
+
+    pub fn u16(&mut self) -> Option<u16> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    pub fn u64(&mut self) -> Option<u64> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    /// Reads a string prefixed by its `u16` length.
+    pub fn str16(&mut self) -> Option<&'a str> {
+        let len = self.u16()? as usize;
+        std::str::from_utf8(self.bytes(len)?).ok()
+    }
+}
+
+/// Appends `v` prefixed by its `u16` length, the counterpart of
+/// [`ByteReader::str16`].
+pub fn put_str16(buf: &mut Vec<u8>, v: &str) {
+    buf.extend_from_slice(&(v.len() as u16).to_le_bytes());
+    buf.extend_from_slice(v.as_bytes());
+}
@@ -0,0 +1,786 @@
+//! Columnar layout of a chunk, selected with
+//! [`SerializeOptions::layout`](crate::serialize::SerializeOptions::layout).
+//!
+//! A columnar chunk (a row group) stores each field of its records as one
+//! column, preceded by a directory locating the columns:
+//!
+//! ```text
+//! record_count     u32
+//! directory_len    u32      length of the directory, checksum included
+//! directory        per field: encoding u8 (with the STORED flag), offset
+//!                  u32, len u32, crc u32
+//!                  (CRC32C of the column bytes), then the column statistics
+//! directory_crc    u32      CRC32C of everything above
+//! columns          the bytes of each column, at its offset (relative to the
+//!                  start of the chunk)
+//! ```
+//!
+//! Statistics are the smallest and largest value of the column: `i64` for
+//! integer, decimal and boolean fields, `f64` for floats, and `u32` length +
+//! bytes for strings. A plain column holds the encoded value of each record
+//! (4 bytes for `i32`, 8 for `i64`, decimals and `f64`, 1 for `bool`), or
+//! for strings one `u32` end offset per record followed by the bytes of
+//! every string.
+//!
+//! A string column with few distinct values (such as `side` or `tif`) is
+//! stored with a dictionary instead, whenever that is smaller:
+//!
+//! ```text
+//! entry_count      u32      at most MAX_DICTIONARY_LEN
+//! entries          the distinct values in increasing order, as a plain
+//!                  string column
+//! codes            u8 per record: the position of its value in the entries
+//! ```
+//!
+//! An `i64`, `i32` or decimal column is stored with delta or frame of reference
+//! encoding instead (see [`crate::integer`]), whichever is the smallest if
+//! smaller than the plain column.
+//!
+//! The chunk checksum in the chunk table covers the whole row group. The
+//! directory and column checksums let a reader fetch a few columns only,
+//! without reading (or trusting) the others. In a compressed file, each
+//! column is compressed on its own, after its encoding and statistics are
+//! computed: the offset, length and checksum of a column are those of its
+//! compressed bytes. A column that compression would not make smaller (such
+//! as random strings under LZ4) is stored as is instead, with the
+//! [`STORED`] flag set on its encoding.
+
+use crate::chunk::{ChunkTable, Layout};
+use crate::codec::{ByteReader, RowWriter};
+use crate::compression::{Compression, STORED};
+use crate::error::{Error, Result};
+use crate::header::FileHeader;
+use crate::index::{field_position, record_key};
+use crate::integer;
+use crate::record::{FieldType, Record};
+use crate::serialize::{read_at, read_chunk_table, read_header};
+use std::borrow::Cow;
+use tokio::fs::File;
+use tokio::io::BufReader;
+
+/// Encoding of the values of a column: one after the other.
+pub const PLAIN: u8 = 0;
+
+/// Encoding of a string column: its distinct values, and a code per record.
+pub const DICTIONARY: u8 = 1;
+
+/// Encoding of an integer column: the differences between consecutive
+/// values, see [`crate::integer`].
+pub const DELTA: u8 = 2;
+
+/// Encoding of an integer column: the offsets of the values from their
+/// minimum, bit-packed, see [`crate::integer`].
+pub const FRAME_OF_REFERENCE: u8 = 3;
+
+/// Most distinct values of a column with a dictionary, so that a code fits
+/// in a byte.
+pub const MAX_DICTIONARY_LEN: usize = 256;
+
+/// Length of the part of a row group preceding its directory.
+pub const ROW_GROUP_PREFIX_LEN: usize = 8;
+
+/// Smallest and largest value of a column.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnStats {
+    /// Integer and boolean columns.
+    Int {
+        min: i64,
+        max: i64,
+    },
+    Float {
+        min: f64,
+        max: f64,
+    },
+    Str {
+        min: String,
+        max: String,
+    },
+}
+
+impl ColumnStats {
+    /// Whether a value whose encoded bytes (as in a record slot, or the bytes
+    /// of a string) are `value` may be in the column.
+    pub fn may_contain(&self, ty: FieldType, value: &[u8]) -> bool {
+        match self {
+            ColumnStats::Int { min, max } => {
+                let value = int_value(ty, value);
+                *min <= value && value <= *max
+            }
+            ColumnStats::Float { min, max } => {
+                let value = f64::from_le_bytes(value.try_into().unwrap());
+                // NaN is never ordered: keep the chunk
+                !(value < *min || value > *max)
+            }
+            ColumnStats::Str { min, max } => min.as_bytes() <= value && value <= max.as_bytes(),
+        }
+    }
+
+    /// Statistics of the encoded `values` of a column of type `ty`.
+    fn of(ty: FieldType, values: &[&[u8]]) -> Self {
+        match ty {
+            FieldType::F64 => {
+                let mut values = values
+                    .iter()
+                    .map(|value| f64::from_le_bytes((*value).try_into().unwrap()));
+                let first = values.next().unwrap_or_default();
+                let (min, max) = values.fold((first, first), |(min, max), value| {
+                    (min.min(value), max.max(value))
+                });
+                ColumnStats::Float { min, max }
+            }
+            FieldType::Str => {
+                let min = values.iter().min().copied().unwrap_or_default();
+                let max = values.iter().max().copied().unwrap_or_default();
+                // Strings of valid encoded records
+                ColumnStats::Str {
+                    min: String::from_utf8_lossy(min).into_owned(),
+                    max: String::from_utf8_lossy(max).into_owned(),
+                }
+            }
+            _ => {
+                let values = values.iter().map(|value| int_value(ty, value));
+                ColumnStats::Int {
+                    min: values.clone().min().unwrap_or_default(),
+                    max: values.max().unwrap_or_default(),
+                }
+            }
+        }
+    }
+
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            ColumnStats::Int { min, max } => {
+                buf.extend_from_slice(&min.to_le_bytes());
+                buf.extend_from_slice(&max.to_le_bytes());
+            }
+            ColumnStats::Float { min, max } => {
+                buf.extend_from_slice(&min.to_le_bytes());
+                buf.extend_from_slice(&max.to_le_bytes());
+            }
+            ColumnStats::Str { min, max } => {
+                for value in [min, max] {
+                    buf.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                    buf.extend_from_slice(value.as_bytes());
+                }
+            }
+        }
+    }
+
+    fn decode(r: &mut ByteReader, ty: FieldType) -> Option<Self> {
+        Some(match ty {
+            FieldType::F64 => ColumnStats::Float {
+                min: f64::from_bits(r.u64()?),
+                max: f64::from_bits(r.u64()?),
+            },
+            FieldType::Str => {
+                let mut string = || {
+                    let len = r.u32()? as usize;
+                    String::from_utf8(r.bytes(len)?.to_vec()).ok()
+                };
+                ColumnStats::Str {
+                    min: string()?,
+                    max: string()?,
+                }
+            }
+            _ => ColumnStats::Int {
+                min: r.u64()? as i64,
+                max: r.u64()? as i64,
+            },
+        })
+    }
+}
+
+/// Value of an integer or boolean field from its encoded bytes.
+fn int_value(ty: FieldType, bytes: &[u8]) -> i64 {
+    match ty {
+        FieldType::I32 => i32::from_le_bytes(bytes.try_into().unwrap()) as i64,
+        FieldType::Bool => bytes[0] as i64,
+        _ => i64::from_le_bytes(bytes.try_into().unwrap()),
+    }
+}
+
+/// Location, checksum and statistics of one column of a row group.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnChunk {
+    pub encoding: u8,
+    /// Stored uncompressed, whatever the compression of the file.
+    pub stored: bool,
+    pub offset: u32,
+    pub len: u32,
+    pub crc: u32,
+    pub stats: ColumnStats,
+}
+
+impl ColumnChunk {
+    /// Compression of the column in a file compressed with `compression`.
+    pub fn compression(&self, compression: Compression) -> Compression {
+        match self.stored {
+            true => Compression::None,
+            false => compression,
+        }
+    }
+
+    /// Checks the bytes read for this column against its checksum;
+    /// `chunk` and `records` locate the row group for the error.
+    pub fn verify(&self, chunk: usize, records: std::ops::Range<u64>, bytes: &[u8]) -> Result<()> {
+        let found = crc32c::crc32c(bytes);
+        if found != self.crc {
+            return Err(Error::ChecksumMismatch {
+                chunk,
+                records,
+                expected: self.crc,
+                found,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The directory at the start of a row group.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnDirectory {
+    pub record_count: u32,
+    /// One entry per field of the schema, in order.
+    pub columns: Vec<ColumnChunk>,
+}
+
+impl ColumnDirectory {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        let start = buf.len();
+        buf.extend_from_slice(&self.record_count.to_le_bytes());
+        // Patched below once the length is known
+        buf.extend_from_slice(&0u32.to_le_bytes());
+        for column in &self.columns {
+            buf.push(column.encoding | if column.stored { STORED } else { 0 });
+            buf.extend_from_slice(&column.offset.to_le_bytes());
+            buf.extend_from_slice(&column.len.to_le_bytes());
+            buf.extend_from_slice(&column.crc.to_le_bytes());
+            column.stats.encode(buf);
+        }
+        let directory_len = (buf.len() - start - ROW_GROUP_PREFIX_LEN + 4) as u32;
+        buf[start + 4..start + 8].copy_from_slice(&directory_len.to_le_bytes());
+        let crc = crc32c::crc32c(&buf[start..]);
+        buf.extend_from_slice(&crc.to_le_bytes());
+    }
+
+    /// Length of the prefix and the directory of a row group, read from its
+    /// first [`ROW_GROUP_PREFIX_LEN`] bytes.
+    pub fn encoded_len(prefix: &[u8]) -> Result<usize> {
+        let mut r = ByteReader::new(prefix);
+        let (Some(_), Some(directory_len)) = (r.u32(), r.u32()) else {
+            return Err(Error::Truncated { what: "row group" });
+        };
+        Ok(ROW_GROUP_PREFIX_LEN + directory_len as usize)
+    }
+
+    /// Decodes the directory at the start of `bytes`, a row group of fields
+    /// of types `types`, checking it against its checksum.
+    pub fn decode(bytes: &[u8], types: &[FieldType]) -> Result<Self> {
+        let len = Self::encoded_len(bytes)?;
+        let covered = len
+            .checked_sub(4)
+            .filter(|&covered| covered >= ROW_GROUP_PREFIX_LEN && len <= bytes.len())
+            .ok_or(Error::Truncated { what: "row group" })?;
+        let expected = u32::from_le_bytes(bytes[covered..len].try_into().unwrap());
+        let found = crc32c::crc32c(&bytes[..covered]);
+        if found != expected {
+            return Err(Error::Corrupt(format!(
+                "Row group directory checksum mismatch: expected {:#010x}, found {:#010x}",
+                expected, found
+            )));
+        }
+
+        let mut r = ByteReader::new(&bytes[..covered]);
+        let record_count = r.u32().ok_or(Error::Truncated { what: "row group" })?;
+        r.u32();
+        let mut columns = Vec::with_capacity(types.len());
+        for &ty in types {
+            let (Some(encoding), Some(offset), Some(len), Some(crc), Some(stats)) = (
+                r.u8(),
+                r.u32(),
+                r.u32(),
+                r.u32(),
+                ColumnStats::decode(&mut r, ty),
+            ) else {
+                return Err(Error::Truncated { what: "row group" });
+            };
+            columns.push(ColumnChunk {
+                encoding: encoding & !STORED,
+                stored: encoding & STORED != 0,
+                offset,
+                len,
+                crc,
+                stats,
+            });
+        }
+        Ok(ColumnDirectory {
+            record_count,
+            columns,
+        })
+    }
+}
+
+/// The values of one column, as stored in a row group.
+pub struct Column<'a> {
+    ty: FieldType,
+    /// The values, or the entries of the dictionary.
+    values: PlainValues<'a>,
+    /// The dictionary code of each value, if the column has a dictionary.
+    codes: Option<&'a [u8]>,
+}
+
+impl<'a> Column<'a> {
+    /// The column of `count` values of type `ty` encoded as `bytes` with
+    /// `encoding`.
+    pub fn new(ty: FieldType, encoding: u8, count: usize, bytes: &'a [u8]) -> Result<Self> {
+        match encoding {
+            PLAIN => Ok(Column {
+                ty,
+                values: PlainValues::new(ty, count, Cow::Borrowed(bytes))?,
+                codes: None,
+            }),
+            DICTIONARY if ty == FieldType::Str => {
+                let entry_count = ByteReader::new(bytes)
+                    .u32()
+                    .ok_or(Error::Truncated { what: "column" })?
+                    as usize;
+                let codes_start = bytes
+                    .len()
+                    .checked_sub(count)
+                    .filter(|&start| start >= 4)
+                    .ok_or(Error::Truncated { what: "column" })?;
+                let codes = &bytes[codes_start..];
+                if let Some(code) = codes.iter().find(|&&code| code as usize >= entry_count) {
+                    return Err(Error::Corrupt(format!(
+                        "Dictionary code {} out of {} entries",
+                        code, entry_count
+                    )));
+                }
+                Ok(Column {
+                    ty,
+                    values: PlainValues::new(
+                        ty,
+                        entry_count,
+                        Cow::Borrowed(&bytes[4..codes_start]),
+                    )?,
+                    codes: Some(codes),
+                })
+            }
+            DELTA | FRAME_OF_REFERENCE
+                if matches!(ty, FieldType::I64 | FieldType::I32 | FieldType::Decimal(_)) =>
+            {
+                let values = match encoding {
+                    DELTA => integer::decode_delta(bytes, count)?,
+                    _ => integer::decode_frame_of_reference(bytes, count)?,
+                };
+                let mut plain = Vec::with_capacity(ty.slot_len() * values.len());
+                for value in values {
+                    match ty {
+                        FieldType::I32 => {
+                            let value = i32::try_from(value).map_err(|_| {
+                                Error::Corrupt(format!("{} out of range of i32", value))
+                            })?;
+                            plain.extend_from_slice(&value.to_le_bytes());
+                        }
+                        _ => plain.extend_from_slice(&value.to_le_bytes()),
+                    }
+                }
+                Ok(Column {
+                    ty,
+                    values: PlainValues::new(ty, count, Cow::Owned(plain))?,
+                    codes: None,
+                })
+            }
+            _ => Err(Error::Corrupt(format!(
+                "Unknown column encoding {} for {} values",
+                encoding,
+                ty.name()
+            ))),
+        }
+    }
+
+    /// Encoded bytes of value number `idx`: the bytes of a string, or what a
+    /// record slot holds for a scalar.
+    pub fn value(&self, idx: usize) -> Result<&[u8]> {
+        match self.codes {
+            Some(codes) => self.values.value(codes[idx] as usize),
+            None => self.values.value(idx),
+        }
+    }
+
+    /// Tells, for each value of the column, whether it equals the encoded
+    /// `value`. A dictionary column looks `value` up once and compares the
+    /// codes.
+    pub fn equal_to(&self, value: &[u8]) -> Result<Vec<bool>> {
+        if let Some(codes) = self.codes {
+            let code = self.values.position(value)?;
+            return Ok(codes.iter().map(|&c| Some(c as usize) == code).collect());
+        }
+        (0..self.values.count)
+            .map(|idx| Ok(self.values.value(idx)? == value))
+            .collect()
+    }
+}
+
+/// Values stored one after the other: the encoded scalars, or for strings
+/// one end offset per value followed by the bytes of every string. Integer
+/// columns with another encoding are decoded into this form.
+struct PlainValues<'a> {
+    ty: FieldType,
+    count: usize,
+    bytes: Cow<'a, [u8]>,
+}
+
+impl<'a> PlainValues<'a> {
+    fn new(ty: FieldType, count: usize, bytes: Cow<'a, [u8]>) -> Result<Self> {
+        let expected = match ty {
+            FieldType::Str => 4 * count,
+            _ => ty.slot_len() * count,
+        };
+        let fits = match ty {
+            FieldType::Str => bytes.len() >= expected,
+            _ => bytes.len() == expected,
+        };
+        if !fits {
+            return Err(Error::Corrupt(format!(
+                "Column of {} {} values takes {} bytes",
+                count,
+                ty.name(),
+                bytes.len()
+            )));
+        }
+        Ok(PlainValues { ty, count, bytes })
+    }
+
+    fn value(&self, idx: usize) -> Result<&[u8]> {
+        if self.ty != FieldType::Str {
+            let len = self.ty.slot_len();
+            return Ok(&self.bytes[idx * len..(idx + 1) * len]);
+        }
+        let end_at = |i: usize| {
+            u32::from_le_bytes(self.bytes[4 * i..4 * i + 4].try_into().unwrap()) as usize
+        };
+        let start = if idx == 0 { 0 } else { end_at(idx - 1) };
+        let end = end_at(idx);
+        let body = &self.bytes[4 * self.count..];
+        body.get(start..end).ok_or_else(|| {
+            Error::Corrupt(format!(
+                "Invalid offset: {}..{} (column data length: {})",
+                start,
+                end,
+                body.len()
+            ))
+        })
+    }
+
+    /// Position of `value` among values sorted in increasing order.
+    fn position(&self, value: &[u8]) -> Result<Option<usize>> {
+        let (mut low, mut high) = (0, self.count);
+        while low < high {
+            let mid = (low + high) / 2;
+            match self.value(mid)?.cmp(value) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return Ok(Some(mid)),
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Encodes `records` as one row group, each column compressed with
+/// `compression` unless that would not make it smaller.
+pub fn encode_row_group<T: Record>(records: &[T], compression: Compression) -> Vec<u8> {
+    let mut rows = Vec::new();
+    let mut ends = Vec::with_capacity(records.len());
+    for record in records {
+        record.encode(&mut rows);
+        ends.push(rows.len());
+    }
+    let mut start = 0;
+    let rows: Vec<&[u8]> = ends
+        .into_iter()
+        .map(|end| {
+            let row = &rows[start..end];
+            start = end;
+            row
+        })
+        .collect();
+
+    let mut slot = 0;
+    let mut columns = Vec::with_capacity(T::SCHEMA.fields.len());
+    for field in T::SCHEMA.fields {
+        // Records encoded just above
+        let values: Vec<&[u8]> = rows
+            .iter()
+            .map(|row| record_key(row, slot, field.ty).unwrap())
+            .collect();
+        let (encoding, bytes, stats) = encode_column(field.ty, &values);
+        let (stored, bytes) = compression.compress_or_store(bytes);
+        columns.push((encoding, stored, bytes, stats));
+        slot += field.ty.slot_len();
+    }
+
+    let mut directory = ColumnDirectory {
+        record_count: records.len() as u32,
+        columns: Vec::with_capacity(columns.len()),
+    };
+    for (encoding, stored, bytes, stats) in &columns {
+        directory.columns.push(ColumnChunk {
+            encoding: *encoding,
+            stored: *stored,
+            offset: 0,
+            len: bytes.len() as u32,
+            crc: crc32c::crc32c(bytes),
+            stats: stats.clone(),
+        });
+    }
+
+    // Offsets do not change the length of the directory
+    let mut offset = {
+        let mut buf = Vec::new();
+        directory.encode(&mut buf);
+        buf.len() as u32
+    };
+    for column in &mut directory.columns {
+        column.offset = offset;
+        offset += column.len;
+    }
+    let mut group = Vec::with_capacity(offset as usize);
+    directory.encode(&mut group);
+    for (_, _, bytes, _) in columns {
+        group.extend_from_slice(&bytes);
+    }
+    group
+}
+
+/// Encodes the values of one column, returning its encoding, its bytes and
+/// its statistics. String columns with few distinct values get a dictionary
+/// when it makes them smaller.
+fn encode_column(ty: FieldType, values: &[&[u8]]) -> (u8, Vec<u8>, ColumnStats) {
+    let stats = ColumnStats::of(ty, values);
+    match ty {
+        FieldType::Str => {
+            if let Some(bytes) = encode_dictionary(values) {
+                return (DICTIONARY, bytes, stats);
+            }
+        }
+        FieldType::I64 | FieldType::I32 | FieldType::Decimal(_) => {
+            if let Some((encoding, bytes)) = encode_integers(ty, values) {
+                return (encoding, bytes, stats);
+            }
+        }
+        _ => {}
+    }
+    let mut bytes = Vec::new();
+    put_plain(&mut bytes, ty, values);
+    (PLAIN, bytes, stats)
+}
+
+fn put_plain(buf: &mut Vec<u8>, ty: FieldType, values: &[&[u8]]) {
+    if ty == FieldType::Str {
+        let mut end = 0u32;
+        for value in values {
+            end += value.len() as u32;
+            buf.extend_from_slice(&end.to_le_bytes());
+        }
+    }
+    for value in values {
+        buf.extend_from_slice(value);
+    }
+}
+
+/// Encodes an integer column with delta or frame of reference encoding,
+/// whichever is the smallest, unless neither is smaller than the plain
+/// column.
+fn encode_integers(ty: FieldType, values: &[&[u8]]) -> Option<(u8, Vec<u8>)> {
+    let values: Vec<i64> = values.iter().map(|value| int_value(ty, value)).collect();
+    let plain_len = ty.slot_len() * values.len();
+    let mut delta = Vec::new();
+    integer::encode_delta(&mut delta, &values);
+    let frame_len = integer::frame_of_reference_len(&values);
+    if frame_len < plain_len && frame_len <= delta.len() {
+        let mut bytes = Vec::with_capacity(frame_len);
+        integer::encode_frame_of_reference(&mut bytes, &values);
+        return Some((FRAME_OF_REFERENCE, bytes));
+    }
+    (delta.len() < plain_len).then_some((DELTA, delta))
+}
+
+/// Encodes a string column as a dictionary, unless it has too many distinct
+/// values or would not get smaller.
+fn encode_dictionary(values: &[&[u8]]) -> Option<Vec<u8>> {
+    let mut entries: Vec<&[u8]> = Vec::new();
+    for value in values {
+        if let Err(at) = entries.binary_search(value) {
+            if entries.len() == MAX_DICTIONARY_LEN {
+                return None;
+            }
+            entries.insert(at, value);
+        }
+    }
+    let plain_len: usize = values.iter().map(|value| 4 + value.len()).sum();
+    let entries_len: usize = entries.iter().map(|entry| 4 + entry.len()).sum();
+    if 4 + entries_len + values.len() >= plain_len {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(4 + entries_len + values.len());
+    bytes.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+    put_plain(&mut bytes, FieldType::Str, &entries);
+    // Every value is an entry
+    bytes.extend(
+        values
+            .iter()
+            .map(|value| entries.binary_search(value).unwrap() as u8),
+    );
+    Some(bytes)
+}
+
+/// Rewrites the row group `group`, of fields of types `types` and columns
+/// compressed with `compression`, as a chunk in the row layout (see
+/// [`crate::chunk`]).
+pub fn row_group_to_rows(
+    group: &[u8],
+    types: &[FieldType],
+    compression: Compression,
+) -> Result<Vec<u8>> {
+    let directory = ColumnDirectory::decode(group, types)?;
+    let count = directory.record_count as usize;
+    let bytes = directory
+        .columns
+        .iter()
+        .map(|column| {
+            let start = column.offset as usize;
+            let bytes = group
+                .get(start..start + column.len as usize)
+                .ok_or_else(|| Error::Corrupt("Column lies outside of its row group".into()))?;
+            column
+                .compression(compression)
+                .decompress(Cow::Borrowed(bytes), "column")
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let columns = bytes
+        .iter()
+        .zip(directory.columns.iter().zip(types))
+        .map(|(bytes, (column, &ty))| Column::new(ty, column.encoding, count, bytes))
+        .collect::<Result<Vec<_>>>()?;
+
+    let fixed_len = types.iter().map(|ty| ty.slot_len()).sum();
+    let mut body = Vec::new();
+    let mut ends = Vec::with_capacity(count.min(group.len()));
+    for idx in 0..count {
+        let mut w = RowWriter::new(&mut body, fixed_len);
+        for column in &columns {
+            w.put_encoded(column.ty, column.value(idx)?);
+        }
+        ends.push(body.len() as u32);
+    }
+
+    let mut chunk = Vec::with_capacity(4 + 4 * count + body.len());
+    chunk.extend_from_slice(&(count as u32).to_le_bytes());
+    for end in ends {
+        chunk.extend_from_slice(&end.to_le_bytes());
+    }
+    chunk.extend_from_slice(&body);
+    Ok(chunk)
+}
+
+/// Reads the directory of row group `idx` of `table`, from a file whose
+/// header is `header`, checking it against its checksum.
+pub(crate) async fn read_directory(
+    file: &mut BufReader<File>,
+    header: &FileHeader,
+    table: &ChunkTable,
+    idx: usize,
+) -> Result<ColumnDirectory> {
+    let entry = &table.entries[idx];
+    let prefix = read_at(file, entry.offset, ROW_GROUP_PREFIX_LEN, "row group").await?;
+    let len = ColumnDirectory::encoded_len(&prefix)?;
+    if len as u64 > entry.len {
+        return Err(Error::Corrupt(format!(
+            "Directory of row group {} is longer than the row group",
+            idx
+        )));
+    }
+    let bytes = read_at(file, entry.offset, len, "row group").await?;
+    let types: Vec<_> = header.fields.iter().map(|(_, ty)| *ty).collect();
+    let directory = ColumnDirectory::decode(&bytes, &types)?;
+    if directory.record_count != entry.record_count {
+        return Err(Error::Corrupt(format!(
+            "Row group {} holds {} records, chunk table announces {}",
+            idx, directory.record_count, entry.record_count
+        )));
+    }
+    Ok(directory)
+}
+
+/// Reads the columns of the fields `fields` of row group `idx` of `table`,
+/// located by its `directory`, checking each against its checksum. Returns
+/// the decompressed bytes of every column, `None` for the fields not read.
+pub(crate) async fn read_columns(
+    file: &mut BufReader<File>,
+    header: &FileHeader,
+    table: &ChunkTable,
+    idx: usize,
+    directory: &ColumnDirectory,
+    fields: &[usize],
+) -> Result<Vec<Option<Vec<u8>>>> {
+    let entry = &table.entries[idx];
+    let mut columns = vec![None; directory.columns.len()];
+    for &field in fields {
+        let column = &directory.columns[field];
+        if column.offset as u64 + column.len as u64 > entry.len {
+            return Err(Error::Corrupt(
+                "Column lies outside of its row group".into(),
+            ));
+        }
+        let offset = entry.offset + column.offset as u64;
+        let bytes = read_at(file, offset, column.len as usize, "column").await?;
+        column.verify(idx, entry.records(), &bytes)?;
+        let bytes = column.compression(header.compression).decompress(
+            Cow::Owned(bytes),
+            &format!("column {} of chunk {}", field, idx),
+        )?;
+        columns[field] = Some(bytes.into_owned());
+    }
+    Ok(columns)
+}
+
+/// The columns of a row group whose `directory` and column bytes, as
+/// returned by [`read_columns`], are given; `None` for the fields not read.
+pub(crate) fn columns_of<'a>(
+    header: &FileHeader,
+    directory: &ColumnDirectory,
+    bytes: &'a [Option<Vec<u8>>],
+) -> Result<Vec<Option<Column<'a>>>> {
+    let count = directory.record_count as usize;
+    bytes
+        .iter()
+        .zip(directory.columns.iter().zip(&header.fields))
+        .map(|(bytes, (column, (_, ty)))| {
+            bytes
+                .as_deref()
+                .map(|bytes| Column::new(*ty, column.encoding, count, bytes))
+                .transpose()
+        })
+        .collect()
+}
+
+/// Statistics of the column of `field` in each row group of the columnar
+/// file at `file_path`. Only the directories of the row groups are read.
+pub async fn column_stats<T: Record>(file_path: &str, field: &str) -> Result<Vec<ColumnStats>> {
+    let position = field_position::<T>(field)?;
+    let mut file = BufReader::new(File::open(file_path).await?);
+    let (header, header_bytes) = read_header::<T>(&mut file).await?;
+    if header.layout != Layout::Columns {
+        return Err(Error::UnsupportedLayout(header.layout));
+    }
+    let table = read_chunk_table(&mut file, &header, &header_bytes).await?;
+    let mut stats = Vec::with_capacity(table.entries.len());
+    for idx in 0..table.entries.len() {
+        let directory = read_directory(&mut file, &header, &table, idx).await?;
+        stats.push(directory.columns[position].stats.clone());
+    }
+    Ok(stats)
+}
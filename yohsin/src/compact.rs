@@ -0,0 +1,185 @@
+//! Merging several files of the same record type into one.
+
+use crate::error::Result;
+use crate::index::{build_index, index_path};
+use crate::record::Record;
+use crate::serialize::{stream_records, SerializeOptions};
+use crate::time_index::{build_time_index, time_index_path};
+use crate::writer::RecordWriter;
+use futures::TryStreamExt;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+
+type SortBy<T> = Box<dyn Fn(&T, &T) -> Ordering + Send + Sync>;
+
+/// Picks the records to keep in two passes over the inputs: every record is
+/// seen by `observe`, then by `keep`, in the same order both times.
+trait Dedup<T>: Send {
+    fn observe(&mut self, idx: u64, record: &T);
+
+    fn keep(&self, idx: u64, record: &T) -> bool;
+}
+
+/// Keeps the last record of each key, remembering only the keys.
+struct LastByKey<F, K> {
+    key: F,
+    last: HashMap<K, u64>,
+}
+
+impl<T, F, K> Dedup<T> for LastByKey<F, K>
+where
+    F: Fn(&T) -> K + Send,
+    K: Hash + Eq + Send,
+{
+    fn observe(&mut self, idx: u64, record: &T) {
+        self.last.insert((self.key)(record), idx);
+    }
+
+    fn keep(&self, idx: u64, record: &T) -> bool {
+        self.last.get(&(self.key)(record)) == Some(&idx)
+    }
+}
+
+/// How [`compact`] orders and filters the records.
+pub struct CompactOptions<T> {
+    sort_by: Option<SortBy<T>>,
+    dedup: Option<Box<dyn Dedup<T>>>,
+    pub serialize: SerializeOptions,
+}
+
+impl<T> Default for CompactOptions<T> {
+    fn default() -> Self {
+        CompactOptions {
+            sort_by: None,
+            dedup: None,
+            serialize: SerializeOptions::default(),
+        }
+    }
+}
+
+impl<T: 'static> CompactOptions<T> {
+    /// Sorts the output by `key`. Records with equal keys keep their input
+    /// order. Sorting holds every record kept in memory.
+    pub fn sort_by_key<K: Ord>(mut self, key: impl Fn(&T) -> K + Send + Sync + 'static) -> Self {
+        self.sort_by = Some(Box::new(move |a, b| key(a).cmp(&key(b))));
+        self
+    }
+
+    /// Keeps a single record per `key`: the last one read, since inputs are
+    /// expected oldest first. The inputs are read twice, and only the keys
+    /// are held in memory.
+    pub fn dedup_by_key<K: Hash + Eq + Send + 'static>(
+        mut self,
+        key: impl Fn(&T) -> K + Send + 'static,
+    ) -> Self {
+        self.dedup = Some(Box::new(LastByKey {
+            key,
+            last: HashMap::new(),
+        }));
+        self
+    }
+}
+
+/// Merges the files `inputs`, oldest first, into `output` and removes the
+/// inputs. Returns the number of records written.
+///
+/// Records are streamed from the inputs, each chunk checked against its
+/// checksum, to a [`RecordWriter`] writing next to the final path, which is
+/// then renamed over it: `output` is replaced atomically, only once every
+/// input was read successfully, and may be one of the inputs. The indexes of
+/// [`SerializeOptions::index_fields`] and
+/// [`SerializeOptions::time_index_fields`] are then rebuilt for `output`, and
+/// the inputs are only removed once all of this is done, together with their
+/// key and time indexes. Only [`CompactOptions::sort_by_key`] holds the
+/// records in memory.
+///
+/// An interrupted compaction starts over. The inputs are removed one at a
+/// time, though: a crash while removing them leaves some behind, whose
+/// records are already in `output` and must not be compacted again.
+pub async fn compact<T: Record>(
+    inputs: &[&str],
+    output: &str,
+    options: CompactOptions<T>,
+) -> Result<usize> {
+    let CompactOptions {
+        sort_by,
+        mut dedup,
+        serialize,
+    } = options;
+
+    if let Some(dedup) = &mut dedup {
+        let mut idx = 0;
+        for input in inputs {
+            let mut stream = std::pin::pin!(stream_records::<T>(Arc::new(input.to_string())));
+            while let Some(record) = stream.try_next().await? {
+                dedup.observe(idx, &record);
+                idx += 1;
+            }
+        }
+    }
+
+    let tmp = Arc::new(format!("{}.compact", output));
+    let journal = Arc::new(format!("{}.compact.journal", output));
+    remove_if_exists(&journal).await?;
+    let mut writer =
+        RecordWriter::<T>::create_with_options(Arc::clone(&tmp), Arc::clone(&journal), &serialize)
+            .await?;
+
+    let mut sorted = Vec::new();
+    let mut record_count = 0;
+    let mut idx = 0;
+    for input in inputs {
+        let mut stream = std::pin::pin!(stream_records::<T>(Arc::new(input.to_string())));
+        while let Some(record) = stream.try_next().await? {
+            let keep = dedup.as_ref().is_none_or(|dedup| dedup.keep(idx, &record));
+            idx += 1;
+            if !keep {
+                continue;
+            }
+            record_count += 1;
+            match sort_by {
+                Some(_) => sorted.push(record),
+                None => writer.push(record).await?,
+            }
+        }
+    }
+    if let Some(sort_by) = &sort_by {
+        sorted.sort_by(sort_by);
+        writer.extend(sorted).await?;
+    }
+    writer.finish().await?;
+    tokio::fs::remove_file(&*journal).await?;
+
+    tokio::fs::rename(&*tmp, output).await?;
+    let dir = match std::path::Path::new(output).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => std::path::Path::new("."),
+    };
+    tokio::fs::File::open(dir).await?.sync_all().await?;
+
+    for field in &serialize.index_fields {
+        build_index::<T>(output, field).await?;
+    }
+    for field in &serialize.time_index_fields {
+        build_time_index::<T>(output, field).await?;
+    }
+
+    for input in inputs.iter().filter(|&&input| input != output) {
+        tokio::fs::remove_file(input).await?;
+        for field in T::SCHEMA.fields {
+            remove_if_exists(&index_path(input, field.name)).await?;
+            remove_if_exists(&time_index_path(input, field.name)).await?;
+        }
+    }
+    tokio::fs::File::open(dir).await?.sync_all().await?;
+    Ok(record_count)
+}
+
+async fn remove_if_exists(path: &str) -> Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,100 @@
+//! Compression of the chunks of a file, selected with
+//! [`SerializeOptions::compression`](crate::serialize::SerializeOptions::compression)
+//! and recorded in the file header.
+//!
+//! A chunk in the row layout is compressed as a whole, after a flags byte. A
+//! row group (see [`crate::columnar`]) keeps its directory as is and
+//! compresses each column on its own, so that a reader can still fetch a few
+//! columns only. A chunk or column that would not get smaller is stored as
+//! is, with the [`STORED`] flag set on its flags byte or on its encoding.
+//! Chunk and column checksums cover the stored bytes: damage is reported
+//! before anything is decompressed.
+
+use crate::error::{Error, Result};
+use std::borrow::Cow;
+use std::io::Read;
+
+/// Flag of a chunk or column stored uncompressed in a compressed file.
+pub const STORED: u8 = 0x80;
+
+/// Codec compressing the chunks of a file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    /// Chunks are stored as encoded.
+    #[default]
+    None,
+    /// LZ4 block format, prefixed with the uncompressed length: fast, with
+    /// a moderate ratio.
+    Lz4,
+    /// Zstandard frames at the fastest level: slower, with a better ratio.
+    Zstd,
+}
+
+impl Compression {
+    /// Stable identifier of the codec in file headers.
+    pub const fn tag(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Lz4 => 1,
+            Compression::Zstd => 2,
+        }
+    }
+
+    pub const fn from_tag(tag: u8) -> Option<Compression> {
+        match tag {
+            0 => Some(Compression::None),
+            1 => Some(Compression::Lz4),
+            2 => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Compresses `bytes`. The result of [`Compression::None`] is `bytes`.
+    pub fn compress(self, bytes: Vec<u8>) -> Vec<u8> {
+        match self {
+            Compression::None => bytes,
+            Compression::Lz4 => lz4_flex::compress_prepend_size(&bytes),
+            Compression::Zstd => ruzstd::encoding::compress_to_vec(
+                bytes.as_slice(),
+                ruzstd::encoding::CompressionLevel::Fastest,
+            ),
+        }
+    }
+
+    /// Compresses `bytes`, unless that would not make them smaller. Returns
+    /// whether they are stored as is, and the stored bytes.
+    pub fn compress_or_store(self, bytes: Vec<u8>) -> (bool, Vec<u8>) {
+        if self == Compression::None {
+            return (false, bytes);
+        }
+        let compressed = self.compress(bytes.clone());
+        match compressed.len() < bytes.len() {
+            true => (false, compressed),
+            false => (true, bytes),
+        }
+    }
+
+    /// Decompresses `bytes`, the compressed form of a `what` (e.g. "chunk
+    /// 3"), reporting invalid input as [`Error::Corrupt`]. The result of
+    /// [`Compression::None`] is `bytes`.
+    pub fn decompress<'a>(self, bytes: Cow<'a, [u8]>, what: &str) -> Result<Cow<'a, [u8]>> {
+        let corrupt = |err: &dyn std::fmt::Display| {
+            Error::Corrupt(format!("Cannot decompress {}: {}", what, err))
+        };
+        match self {
+            Compression::None => Ok(bytes),
+            Compression::Lz4 => lz4_flex::decompress_size_prepended(&bytes)
+                .map(Cow::Owned)
+                .map_err(|err| corrupt(&err)),
+            Compression::Zstd => {
+                let mut decoder = ruzstd::decoding::StreamingDecoder::new(&*bytes)
+                    .map_err(|err| corrupt(&err))?;
+                let mut decompressed = Vec::new();
+                decoder
+                    .read_to_end(&mut decompressed)
+                    .map_err(|err| corrupt(&err))?;
+                Ok(Cow::Owned(decompressed))
+            }
+        }
+    }
+}
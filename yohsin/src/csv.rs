@@ -0,0 +1,312 @@
+//! Loading and writing CSV files of any [`Record`] type.
+//!
+//! Files follow RFC 4180, with a configurable delimiter: a value holding the
+//! delimiter, a double quote or a line break is enclosed in double quotes,
+//! and its double quotes are doubled (`"say ""hi"", then leave"`). Lines end
+//! with LF or CRLF; a quoted value may span several lines. Blank lines are
+//! skipped.
+
+use crate::error::{Error, Result};
+use crate::record::{Record, Schema};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::sync::Arc;
+
+/// Options of [`load_from_file_with_options`] and
+/// [`write_to_file_with_options`].
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    /// Byte separating the values of a line, `,` by default. Must be an
+    /// ASCII character other than a double quote or a line break.
+    pub delimiter: u8,
+}
+
+impl CsvOptions {
+    /// Checks that the options can be used, see [`CsvOptions::delimiter`].
+    pub fn validate(&self) -> Result<()> {
+        match self.delimiter {
+            b'"' | b'\r' | b'\n' => Err(Error::InvalidOptions(
+                "a CSV delimiter cannot be a double quote or a line break",
+            )),
+            byte if !byte.is_ascii() => Err(Error::InvalidOptions(
+                "a CSV delimiter must be an ASCII character",
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions { delimiter: b',' }
+    }
+}
+
+/// One line of a CSV file, or several when a quoted value spans lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvRecord {
+    /// Line number, from 1, the record starts on.
+    pub line: usize,
+    pub fields: Vec<String>,
+}
+
+/// Splits CSV input into records, unquoting their values.
+pub struct CsvReader<R> {
+    reader: R,
+    delimiter: u8,
+    /// Lines read so far.
+    line: usize,
+    buf: Vec<u8>,
+}
+
+impl<R: BufRead> CsvReader<R> {
+    /// Reads `reader`, failing if `options` are invalid.
+    pub fn new(reader: R, options: &CsvOptions) -> Result<Self> {
+        options.validate()?;
+        Ok(CsvReader {
+            reader,
+            delimiter: options.delimiter,
+            line: 0,
+            buf: Vec::new(),
+        })
+    }
+
+    /// Appends the next line to the buffer, returning false at the end of
+    /// the input.
+    fn read_line(&mut self) -> Result<bool> {
+        if self.reader.read_until(b'\n', &mut self.buf)? == 0 {
+            return Ok(false);
+        }
+        self.line += 1;
+        Ok(true)
+    }
+
+    fn read_record(&mut self) -> Result<Option<CsvRecord>> {
+        loop {
+            self.buf.clear();
+            if !self.read_line()? {
+                return Ok(None);
+            }
+            if line_len(&self.buf) > 0 {
+                break;
+            }
+        }
+
+        let start = self.line;
+        let mut fields = Vec::new();
+        let mut field = Vec::new();
+        let mut pos = 0;
+        loop {
+            let column = fields.len() + 1;
+            let malformed = |line, value: &[u8], reason| Error::CsvMalformed {
+                line,
+                column,
+                field: None,
+                value: String::from_utf8_lossy(value).into_owned(),
+                reason,
+            };
+            field.clear();
+
+            let field_start = pos;
+            if self.buf.get(pos) == Some(&b'"') {
+                pos += 1;
+                loop {
+                    match self.buf.get(pos).copied() {
+                        Some(b'"') if self.buf.get(pos + 1) == Some(&b'"') => {
+                            field.push(b'"');
+                            pos += 2;
+                        }
+                        Some(b'"') => {
+                            pos += 1;
+                            break;
+                        }
+                        Some(byte) => {
+                            field.push(byte);
+                            pos += 1;
+                        }
+                        // The line break belongs to the value, which goes on
+                        // on the next line
+                        None if self.read_line()? => {}
+                        None => {
+                            // Reported up to its first line break, as it
+                            // takes the rest of the input
+                            let first_line = field.split(|&byte| byte == b'\n').next();
+                            return Err(malformed(
+                                start,
+                                first_line.unwrap_or_default(),
+                                "unterminated quoted value",
+                            ));
+                        }
+                    }
+                }
+                let end = line_len(&self.buf);
+                if pos < end && self.buf[pos] != self.delimiter {
+                    // Reported up to the next delimiter, quotes included
+                    let raw_len = self.buf[pos..end]
+                        .iter()
+                        .position(|&byte| byte == self.delimiter)
+                        .unwrap_or(end - pos);
+                    return Err(malformed(
+                        self.line,
+                        &self.buf[field_start..pos + raw_len],
+                        "unexpected character after a closing quote",
+                    ));
+                }
+            } else {
+                let end = line_len(&self.buf);
+                let len = self.buf[pos..end]
+                    .iter()
+                    .position(|&byte| byte == self.delimiter)
+                    .unwrap_or(end - pos);
+                field.extend_from_slice(&self.buf[pos..pos + len]);
+                pos += len;
+                if field.contains(&b'"') {
+                    return Err(malformed(
+                        self.line,
+                        &field,
+                        "double quote in an unquoted value",
+                    ));
+                }
+            }
+
+            let value = std::str::from_utf8(&field)
+                .map_err(|_| malformed(self.line, &field, "invalid UTF-8"))?;
+            fields.push(value.to_string());
+            if pos >= line_len(&self.buf) {
+                return Ok(Some(CsvRecord {
+                    line: start,
+                    fields,
+                }));
+            }
+            // Skip the delimiter
+            pos += 1;
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for CsvReader<R> {
+    type Item = Result<CsvRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+/// Length of `line` without its line ending, LF or CRLF.
+fn line_len(line: &[u8]) -> usize {
+    match line {
+        [.., b'\r', b'\n'] => line.len() - 2,
+        [.., b'\n'] => line.len() - 1,
+        _ => line.len(),
+    }
+}
+
+/// Names the field of a [`Error::CsvMalformed`] after the column of
+/// `schema` it was found in.
+fn in_schema(err: Error, schema: &Schema) -> Error {
+    match err {
+        Error::CsvMalformed {
+            line,
+            column,
+            value,
+            reason,
+            ..
+        } => Error::CsvMalformed {
+            line,
+            column,
+            field: schema.fields.get(column - 1).map(|field| field.name),
+            value,
+            reason,
+        },
+        err => err,
+    }
+}
+
+/// Loads every row of a CSV file with the default [`CsvOptions`].
+pub fn load_from_file<T: Record>(file_path: &str) -> Result<Arc<[T]>> {
+    load_from_file_with_options(file_path, &CsvOptions::default())
+}
+
+/// Loads every row of a CSV file, skipping the header line.
+pub fn load_from_file_with_options<T: Record>(
+    file_path: &str,
+    options: &CsvOptions,
+) -> Result<Arc<[T]>> {
+    let file = File::open(file_path)?;
+    let reader = CsvReader::new(BufReader::new(file), options)?;
+
+    let mut data_list = Vec::new(); // Temporary vector to collect data
+
+    for record in reader.skip(1) {
+        let record = record.map_err(|e| in_schema(e, T::SCHEMA))?;
+        let parts: Vec<&str> = record.fields.iter().map(String::as_str).collect();
+
+        let data = T::from_csv_fields(&parts).map_err(|e| e.at_line(record.line))?;
+        data_list.push(data);
+    }
+
+    Ok(Arc::from(data_list))
+}
+
+/// Writes `data` to a CSV file with the default [`CsvOptions`].
+pub fn write_to_file<T: Record>(file_path: &str, data: &[T]) -> Result<()> {
+    write_to_file_with_options(file_path, data, &CsvOptions::default())
+}
+
+/// Writes `data` to a CSV file, with the schema field names as header.
+/// Values are quoted only when needed.
+pub fn write_to_file_with_options<T: Record>(
+    file_path: &str,
+    data: &[T],
+    options: &CsvOptions,
+) -> Result<()> {
+    options.validate()?;
+    let file = File::create(file_path)?;
+    let mut writer = BufWriter::new(file);
+
+    // Write the CSV header
+    let header = T::SCHEMA.fields.iter().map(|field| field.name);
+    write_line(&mut writer, header, options.delimiter)?;
+
+    // Write each record as a CSV line
+    for record in data {
+        let fields = record.to_csv_fields();
+        write_line(
+            &mut writer,
+            fields.iter().map(String::as_str),
+            options.delimiter,
+        )?;
+    }
+
+    // Ensure all data is flushed to the file
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_line<'a>(
+    writer: &mut impl Write,
+    values: impl Iterator<Item = &'a str>,
+    delimiter: u8,
+) -> Result<()> {
+    for (idx, value) in values.enumerate() {
+        if idx > 0 {
+            writer.write_all(&[delimiter])?;
+        }
+        writer.write_all(quote(value, delimiter).as_bytes())?;
+    }
+    writer.write_all(b"\n")?;
+    Ok(())
+}
+
+/// `value` as a CSV value: enclosed in double quotes, its own doubled, if it
+/// holds the delimiter, a double quote or a line break.
+pub fn quote(value: &str, delimiter: u8) -> Cow<'_, str> {
+    let needs_quotes = value
+        .bytes()
+        .any(|byte| matches!(byte, b'"' | b'\r' | b'\n') || byte == delimiter);
+    if !needs_quotes {
+        return Cow::Borrowed(value);
+    }
+    Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+}
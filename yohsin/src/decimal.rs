@@ -0,0 +1,178 @@
+//! Fixed-point decimal numbers, stored as scaled integers.
+//!
+//! A [`Decimal<SCALE>`] holds its value multiplied by `10^SCALE` in an
+//! `i64`: `18.83` at scale 2 is stored as `1883`. Parsing is exact and
+//! rejects anything that does not fit the scale, so that a value read from
+//! CSV is written back with the same digits, always `SCALE` of them after
+//! the point.
+
+use crate::codec::{RowReader, RowWriter};
+use crate::error::Result;
+use crate::record::{FieldType, FieldValue};
+use std::fmt;
+use std::str::FromStr;
+
+/// Largest scale whose factor, `10^scale`, fits in an `i64`.
+pub const MAX_SCALE: u32 = 18;
+
+/// Price of an order, in hundredths as generated by `data_baker`.
+pub type Price = Decimal<2>;
+
+/// A decimal number with exactly `SCALE` digits after the point.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Decimal<const SCALE: u32>(i64);
+
+impl<const SCALE: u32> Decimal<SCALE> {
+    /// `10^SCALE`, the stored value of one.
+    pub const FACTOR: i64 = {
+        assert!(SCALE <= MAX_SCALE, "Decimal scale exceeds MAX_SCALE");
+        10i64.pow(SCALE)
+    };
+
+    /// The decimal `mantissa / 10^SCALE`.
+    pub const fn new(mantissa: i64) -> Self {
+        Decimal(mantissa)
+    }
+
+    /// The value multiplied by `10^SCALE`, as stored.
+    pub const fn mantissa(self) -> i64 {
+        self.0
+    }
+
+    /// Nearest `f64`, for arithmetic that does not need to be exact.
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / Self::FACTOR as f64
+    }
+}
+
+/// Reason a string is not a valid decimal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseDecimalError {
+    /// No digits before the point.
+    Empty,
+    /// A character other than a digit, or a point with no digits after it.
+    InvalidDigit,
+    /// More digits after the point than the scale allows.
+    TooPrecise { scale: u32 },
+    /// The scaled value does not fit in an `i64`.
+    Overflow,
+}
+
+impl fmt::Display for ParseDecimalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseDecimalError::Empty => write!(f, "no digits before the point"),
+            ParseDecimalError::InvalidDigit => write!(f, "invalid digit"),
+            ParseDecimalError::TooPrecise { scale } => {
+                write!(f, "more than {} digits after the point", scale)
+            }
+            ParseDecimalError::Overflow => write!(f, "out of range"),
+        }
+    }
+}
+
+impl std::error::Error for ParseDecimalError {}
+
+/// Parses `raw`, an optionally signed number with at most `scale` digits
+/// after the point, into its value multiplied by `10^scale`.
+pub fn parse_mantissa(raw: &str, scale: u32) -> Result<i64, ParseDecimalError> {
+    let (negative, digits) = match raw.as_bytes().first() {
+        Some(b'-') => (true, &raw[1..]),
+        Some(b'+') => (false, &raw[1..]),
+        _ => (false, raw),
+    };
+    let (int_part, frac_part) = match digits.split_once('.') {
+        Some((_, "")) => return Err(ParseDecimalError::InvalidDigit),
+        Some((int_part, frac_part)) => (int_part, frac_part),
+        None => (digits, ""),
+    };
+    if int_part.is_empty() {
+        return Err(ParseDecimalError::Empty);
+    }
+    if !frac_part.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(ParseDecimalError::InvalidDigit);
+    }
+    if frac_part.len() > scale as usize {
+        return Err(ParseDecimalError::TooPrecise { scale });
+    }
+
+    // Accumulated with the sign applied, so that `i64::MIN` parses too
+    let sign = if negative { -1 } else { 1 };
+    let padding = scale as usize - frac_part.len();
+    let mut mantissa = 0i64;
+    for byte in int_part
+        .bytes()
+        .chain(frac_part.bytes())
+        .chain(std::iter::repeat_n(b'0', padding))
+    {
+        if !byte.is_ascii_digit() {
+            return Err(ParseDecimalError::InvalidDigit);
+        }
+        mantissa = mantissa
+            .checked_mul(10)
+            .and_then(|m| m.checked_add(sign * (byte - b'0') as i64))
+            .ok_or(ParseDecimalError::Overflow)?;
+    }
+    Ok(mantissa)
+}
+
+impl<const SCALE: u32> FromStr for Decimal<SCALE> {
+    type Err = ParseDecimalError;
+
+    fn from_str(raw: &str) -> Result<Self, ParseDecimalError> {
+        parse_mantissa(raw, SCALE).map(Decimal)
+    }
+}
+
+/// Canonical form: `-` for negative values, then every digit before the
+/// point, then exactly `SCALE` after it, e.g. `-0.05` at scale 2.
+impl<const SCALE: u32> fmt::Display for Decimal<SCALE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        let factor = Self::FACTOR as u64;
+        if SCALE == 0 {
+            return write!(f, "{}{}", sign, abs);
+        }
+        write!(
+            f,
+            "{}{}.{:0width$}",
+            sign,
+            abs / factor,
+            abs % factor,
+            width = SCALE as usize
+        )
+    }
+}
+
+impl<const SCALE: u32> FieldValue for Decimal<SCALE> {
+    const TYPE: FieldType = {
+        assert!(SCALE <= MAX_SCALE, "Decimal scale exceeds MAX_SCALE");
+        FieldType::Decimal(SCALE as u8)
+    };
+    type Ref<'a> = Self;
+
+    fn put(&self, w: &mut RowWriter) {
+        w.put_i64(self.0);
+    }
+
+    fn get(r: &mut RowReader) -> Result<Self> {
+        Ok(Decimal(r.get_i64()))
+    }
+
+    fn get_ref<'a>(r: &mut RowReader<'a>) -> Result<Self> {
+        Ok(Decimal(r.get_i64()))
+    }
+
+    fn from_ref(v: Self) -> Self {
+        v
+    }
+
+    fn parse(raw: &str) -> Result<Self, String> {
+        raw.parse().map_err(|e: ParseDecimalError| e.to_string())
+    }
+
+    fn format(&self) -> String {
+        self.to_string()
+    }
+}
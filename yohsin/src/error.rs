@@ -0,0 +1,263 @@
+//! Error type shared by every fallible operation of the crate.
+
+use crate::chunk::Layout;
+use crate::header::FORMAT_VERSION;
+use crate::record::{FieldType, RecordSize};
+use std::fmt;
+use std::ops::Range;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    /// The file ended before the structure being read (`what`) did.
+    Truncated {
+        what: &'static str,
+    },
+    /// The file does not start with [`crate::header::MAGIC`].
+    BadMagic([u8; 8]),
+    UnsupportedVersion(u16),
+    /// The file was written on a machine with a different byte order.
+    EndiannessMismatch(u8),
+    /// The file holds records of another schema than the one requested.
+    SchemaMismatch {
+        expected: &'static str,
+        expected_hash: u64,
+        found_hash: u64,
+        found_fields: Vec<String>,
+    },
+    RecordSizeMismatch {
+        expected: RecordSize,
+        found: RecordSize,
+    },
+    /// The header is structurally invalid (unknown type tag, bad UTF-8...).
+    MalformedHeader(&'static str),
+    /// A chunk does not match its checksum; `records` are the records it holds.
+    ChecksumMismatch {
+        chunk: usize,
+        records: Range<u64>,
+        expected: u32,
+        found: u32,
+    },
+    /// The header or the chunk table does not match the table checksum.
+    ChunkTableChecksumMismatch {
+        expected: u32,
+        found: u32,
+    },
+    /// The file passed its checksums but its content is inconsistent.
+    Corrupt(String),
+    /// The requested records are not all in the file.
+    RangeOutOfBounds {
+        start: usize,
+        end: usize,
+        len: usize,
+    },
+    /// The records are no longer in the store: their segment was archived
+    /// or deleted.
+    MissingRecords {
+        start: u64,
+        end: u64,
+    },
+    /// The schema has no field with this name.
+    UnknownField {
+        schema: &'static str,
+        field: String,
+    },
+    /// The field has a type that cannot be used this way, e.g. a string as a
+    /// time field.
+    UnsupportedFieldType {
+        field: &'static str,
+        ty: FieldType,
+    },
+    /// The operation needs a file with another layout.
+    UnsupportedLayout(Layout),
+    /// A key (or a predicate value) cannot be parsed as its field.
+    InvalidKey {
+        field: &'static str,
+        key: String,
+        reason: String,
+    },
+    /// The index no longer matches the file it was built for.
+    StaleIndex {
+        path: String,
+    },
+    /// Options that cannot be used together, or out of their range.
+    InvalidOptions(&'static str),
+    /// A CSV line does not have one value per field of the schema.
+    CsvFieldCount {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// A CSV value is not properly quoted, or not valid UTF-8. The field is
+    /// known when the file is loaded as records of a schema.
+    CsvMalformed {
+        line: usize,
+        column: usize,
+        field: Option<&'static str>,
+        value: String,
+        reason: &'static str,
+    },
+    /// A CSV value cannot be parsed as its field.
+    CsvParse {
+        line: usize,
+        column: usize,
+        field: &'static str,
+        value: String,
+        reason: String,
+    },
+}
+
+impl Error {
+    /// Sets the line number of CSV errors raised without knowing it.
+    pub fn at_line(mut self, at: usize) -> Self {
+        if let Error::CsvFieldCount { line, .. } | Error::CsvParse { line, .. } = &mut self {
+            *line = at;
+        }
+        self
+    }
+
+    /// Converts an I/O error, reporting an unexpected end of file as
+    /// [`Error::Truncated`].
+    pub(crate) fn reading(what: &'static str) -> impl FnOnce(std::io::Error) -> Error {
+        move |e| match e.kind() {
+            std::io::ErrorKind::UnexpectedEof => Error::Truncated { what },
+            _ => Error::Io(e),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Truncated { what } => write!(f, "File is too short to hold the {}", what),
+            Error::BadMagic(magic) => write!(f, "Not a yohsin file (magic {:?})", magic),
+            Error::UnsupportedVersion(v) => write!(
+                f,
+                "Unsupported format version {} (expected {})",
+                v, FORMAT_VERSION
+            ),
+            Error::EndiannessMismatch(flag) => {
+                write!(f, "File was written with endianness flag {}", flag)
+            }
+            Error::SchemaMismatch {
+                expected,
+                expected_hash,
+                found_hash,
+                found_fields,
+            } => write!(
+                f,
+                "Schema mismatch: expected {} ({:#018x}), file holds {:#018x} with fields [{}]",
+                expected,
+                expected_hash,
+                found_hash,
+                found_fields.join(", ")
+            ),
+            Error::RecordSizeMismatch { expected, found } => write!(
+                f,
+                "Record size mismatch: expected {:?}, file holds {:?}",
+                expected, found
+            ),
+            Error::MalformedHeader(what) => write!(f, "Malformed header: {}", what),
+            Error::ChecksumMismatch {
+                chunk,
+                records,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Checksum mismatch in chunk {} (records {}..{}): expected {:#010x}, found {:#010x}",
+                chunk, records.start, records.end, expected, found
+            ),
+            Error::ChunkTableChecksumMismatch { expected, found } => write!(
+                f,
+                "Checksum mismatch in header or chunk table: expected {:#010x}, found {:#010x}",
+                expected, found
+            ),
+            Error::Corrupt(what) => write!(f, "Corrupt file: {}", what),
+            Error::RangeOutOfBounds { start, end, len } => write!(
+                f,
+                "Invalid range: {}..{} (file contains {} records)",
+                start, end, len
+            ),
+            Error::MissingRecords { start, end } => {
+                write!(f, "Records {}..{} are no longer in the store", start, end)
+            }
+            Error::UnknownField { schema, field } => {
+                write!(f, "{} has no field `{}`", schema, field)
+            }
+            Error::UnsupportedFieldType { field, ty } => write!(
+                f,
+                "Field `{}` of type {} cannot be used here",
+                field,
+                ty.name()
+            ),
+            Error::UnsupportedLayout(layout) => {
+                write!(
+                    f,
+                    "Operation not supported on a file with the {:?} layout",
+                    layout
+                )
+            }
+            Error::InvalidKey { field, key, reason } => {
+                write!(f, "Invalid key {:?} for `{}`: {}", key, field, reason)
+            }
+            Error::StaleIndex { path } => write!(
+                f,
+                "Index {} does not match its file and must be rebuilt",
+                path
+            ),
+            Error::InvalidOptions(reason) => write!(f, "Invalid options: {}", reason),
+            Error::CsvFieldCount {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Line {}: expected {} fields, found {}",
+                line, expected, found
+            ),
+            Error::CsvMalformed {
+                line,
+                column,
+                field,
+                value,
+                reason,
+            } => {
+                write!(f, "Line {}, column {}", line, column)?;
+                if let Some(field) = field {
+                    write!(f, " (`{}`)", field)?;
+                }
+                write!(f, ": malformed value {:?}: {}", value, reason)
+            }
+            Error::CsvParse {
+                line,
+                column,
+                field,
+                value,
+                reason,
+            } => write!(
+                f,
+                "Line {}, column {} (`{}`): invalid value {:?}: {}",
+                line, column, field, value, reason
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
@@ -0,0 +1,79 @@
+//! Crash injection for the fault-tolerance tests, only compiled with the
+//! `failpoints` feature.
+//!
+//! A failpoint is armed for one data file with a budget of steps. Every
+//! checkpoint the writer passes while serializing that file consumes one step;
+//! when the budget runs out the writer stops as if the process had died there,
+//! and every later checkpoint for the same file fails too, so that concurrent
+//! tasks stop at their next checkpoint as well.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+
+/// Error returned by the writer when an armed failpoint fires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InjectedCrash;
+
+impl fmt::Display for InjectedCrash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Injected crash")
+    }
+}
+
+impl std::error::Error for InjectedCrash {}
+
+impl From<InjectedCrash> for crate::Error {
+    fn from(crash: InjectedCrash) -> Self {
+        crate::Error::Io(std::io::Error::other(crash))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Armed(usize),
+    Crashed,
+}
+
+fn registry() -> &'static Mutex<HashMap<String, State>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<String, State>>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// Makes the serialization of `file_path` crash at its `steps`-th checkpoint
+/// (the first one for `steps == 0`).
+pub fn arm(file_path: &str, steps: usize) {
+    registry()
+        .lock()
+        .unwrap()
+        .insert(file_path.to_string(), State::Armed(steps));
+}
+
+/// Removes the failpoint of `file_path`, whether it fired or not.
+pub fn disarm(file_path: &str) {
+    registry().lock().unwrap().remove(file_path);
+}
+
+/// Whether `err` comes from a failpoint.
+pub fn is_injected(err: &crate::Error) -> bool {
+    matches!(err, crate::Error::Io(e)
+        if e.get_ref().is_some_and(|inner| inner.is::<InjectedCrash>()))
+}
+
+/// Consumes one step of the failpoint of `file_path`, if armed.
+pub(crate) fn check(file_path: &str) -> Result<(), InjectedCrash> {
+    let mut registry = registry().lock().unwrap();
+    let Some(state) = registry.get_mut(file_path) else {
+        return Ok(());
+    };
+    match *state {
+        State::Armed(0) | State::Crashed => {
+            *state = State::Crashed;
+            Err(InjectedCrash)
+        }
+        State::Armed(steps) => {
+            *state = State::Armed(steps - 1);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,223 @@
+//! Filtering records while they are read, before they are materialized.
+
+use crate::chunk::{chunk_records, Layout};
+use crate::columnar::{columns_of, read_columns, read_directory, Column, ColumnDirectory};
+use crate::error::Result;
+use crate::index::{field_of, field_position, parse_key, record_key};
+use crate::record::{FieldType, Record};
+use crate::serialize::{read_chunk_table, read_header, read_verified_chunk};
+use std::sync::Arc;
+use tokio::fs::File;
+use tokio::io::BufReader;
+
+/// A condition on the fields of a record, e.g.
+/// `Predicate::eq("symbol", "AAPL").and(Predicate::eq("side", "BUY"))`.
+///
+/// Values are written as in a CSV file. Only the fields named by the
+/// predicate are read from each encoded record.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    /// The field equals the value.
+    Eq {
+        field: String,
+        value: String,
+    },
+    And(Box<Predicate>, Box<Predicate>),
+    Or(Box<Predicate>, Box<Predicate>),
+}
+
+impl Predicate {
+    pub fn eq(field: &str, value: &str) -> Self {
+        Predicate::Eq {
+            field: field.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    pub fn and(self, other: Predicate) -> Self {
+        Predicate::And(Box::new(self), Box::new(other))
+    }
+
+    pub fn or(self, other: Predicate) -> Self {
+        Predicate::Or(Box::new(self), Box::new(other))
+    }
+
+    /// Resolves the fields against the schema of `T` and parses the values.
+    fn compile<T: Record>(&self) -> Result<Compiled> {
+        Ok(match self {
+            Predicate::Eq { field, value } => {
+                let (field, slot) = field_of::<T>(field)?;
+                Compiled::Eq {
+                    position: field_position::<T>(field.name)?,
+                    slot,
+                    ty: field.ty,
+                    value: parse_key(field, value)?,
+                }
+            }
+            Predicate::And(a, b) => {
+                Compiled::And(Box::new(a.compile::<T>()?), Box::new(b.compile::<T>()?))
+            }
+            Predicate::Or(a, b) => {
+                Compiled::Or(Box::new(a.compile::<T>()?), Box::new(b.compile::<T>()?))
+            }
+        })
+    }
+}
+
+/// A [`Predicate`] evaluated directly on encoded records.
+enum Compiled {
+    Eq {
+        position: usize,
+        slot: usize,
+        ty: FieldType,
+        value: Vec<u8>,
+    },
+    And(Box<Compiled>, Box<Compiled>),
+    Or(Box<Compiled>, Box<Compiled>),
+}
+
+impl Compiled {
+    fn matches(&self, record: &[u8]) -> Result<bool> {
+        Ok(match self {
+            Compiled::Eq {
+                slot, ty, value, ..
+            } => record_key(record, *slot, *ty)? == value.as_slice(),
+            Compiled::And(a, b) => a.matches(record)? && b.matches(record)?,
+            Compiled::Or(a, b) => a.matches(record)? || b.matches(record)?,
+        })
+    }
+
+    /// Whether a row group whose directory is `directory` may hold a
+    /// matching record, judging from the statistics of its columns.
+    fn may_match(&self, directory: &ColumnDirectory) -> bool {
+        match self {
+            Compiled::Eq {
+                position,
+                ty,
+                value,
+                ..
+            } => directory.columns[*position].stats.may_contain(*ty, value),
+            Compiled::And(a, b) => a.may_match(directory) && b.may_match(directory),
+            Compiled::Or(a, b) => a.may_match(directory) || b.may_match(directory),
+        }
+    }
+
+    /// Adds the positions of the fields the predicate names to `positions`.
+    fn positions(&self, positions: &mut Vec<usize>) {
+        match self {
+            Compiled::Eq { position, .. } => positions.push(*position),
+            Compiled::And(a, b) | Compiled::Or(a, b) => {
+                a.positions(positions);
+                b.positions(positions);
+            }
+        }
+    }
+
+    /// Tells which records of a row group match, given the columns of the
+    /// row group, which must include the fields the predicate names.
+    fn matching_rows(&self, columns: &[Option<Column>]) -> Result<Vec<bool>> {
+        Ok(match self {
+            // Every column of the predicate was read
+            Compiled::Eq {
+                position, value, ..
+            } => columns[*position].as_ref().unwrap().equal_to(value)?,
+            Compiled::And(a, b) => {
+                let (a, b) = (a.matching_rows(columns)?, b.matching_rows(columns)?);
+                a.iter().zip(b).map(|(a, b)| *a && b).collect()
+            }
+            Compiled::Or(a, b) => {
+                let (a, b) = (a.matching_rows(columns)?, b.matching_rows(columns)?);
+                a.iter().zip(b).map(|(a, b)| *a || b).collect()
+            }
+        })
+    }
+}
+
+/// Reads every chunk of the file at `file_path`, checking it against its
+/// checksum, and keeps the records `select` returns from the encoded bytes of
+/// the records matching `predicate`, if any.
+///
+/// In a columnar file, the predicate is first evaluated on the columns it
+/// names: the row groups whose statistics rule out a match are skipped, and
+/// so are those without a matching record.
+async fn read_matching<T: Record>(
+    file_path: &str,
+    predicate: Option<&Compiled>,
+    mut select: impl FnMut(&[u8]) -> Result<Option<T>>,
+) -> Result<Arc<[T]>> {
+    let mut file = BufReader::new(File::open(file_path).await?);
+    let (header, header_bytes) = read_header::<T>(&mut file).await?;
+    let table = read_chunk_table(&mut file, &header, &header_bytes).await?;
+    let mut positions = Vec::new();
+    if let Some(predicate) = predicate {
+        predicate.positions(&mut positions);
+    }
+
+    let mut retrieved_data = Vec::new();
+    for idx in 0..table.entries.len() {
+        let rows = match (predicate, header.layout) {
+            (Some(predicate), Layout::Columns) => {
+                let directory = read_directory(&mut file, &header, &table, idx).await?;
+                if !predicate.may_match(&directory) {
+                    continue;
+                }
+                let bytes =
+                    read_columns(&mut file, &header, &table, idx, &directory, &positions).await?;
+                let columns = columns_of(&header, &directory, &bytes)?;
+                let rows = predicate.matching_rows(&columns)?;
+                if !rows.contains(&true) {
+                    continue;
+                }
+                Some(rows)
+            }
+            _ => None,
+        };
+        let chunk = read_verified_chunk(&mut file, &header, &table, idx).await?;
+        for (row, bytes) in chunk_records(&chunk)?.into_iter().enumerate() {
+            let matches = match (&rows, predicate) {
+                (Some(rows), _) => rows[row],
+                (None, Some(predicate)) => predicate.matches(bytes)?,
+                (None, None) => true,
+            };
+            if matches {
+                retrieved_data.extend(select(bytes)?);
+            }
+        }
+    }
+    Ok(Arc::from(retrieved_data))
+}
+
+/// Deserializes the records of a file matching `predicate`, in file order.
+///
+/// The predicate is evaluated on the encoded records, reading only the fields
+/// it names; only the matching records are decoded. In a columnar file it is
+/// evaluated on the columns of these fields, comparing dictionary codes
+/// rather than strings, and the row groups without a match are skipped.
+pub async fn deserialize_where<T>(file_path: Arc<String>, predicate: &Predicate) -> Result<Arc<[T]>>
+where
+    T: Record,
+{
+    let predicate = predicate.compile::<T>()?;
+    read_matching(&file_path, Some(&predicate), |bytes| {
+        Ok(Some(T::decode(bytes)?))
+    })
+    .await
+}
+
+/// Deserializes the records of a file for which `predicate` holds, in file
+/// order.
+///
+/// The predicate gets each record as a view borrowing the encoded bytes (see
+/// [`Record::view`]): nothing is allocated for the records it rejects, and
+/// the others are copied out of their view.
+pub async fn deserialize_filtered<T, P>(file_path: Arc<String>, predicate: P) -> Result<Arc<[T]>>
+where
+    T: Record,
+    P: for<'a> Fn(T::View<'a>) -> bool,
+{
+    read_matching(&file_path, None, |bytes| {
+        let view = T::view(bytes)?;
+        Ok(predicate(view).then(|| T::from_view(view)))
+    })
+    .await
+}
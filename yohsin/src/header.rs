@@ -0,0 +1,240 @@
+//! Self-describing header at the start of every dump file.
+//!
+//! ```text
+//! magic            8 bytes  b"YOHSIN\0\0"
+//! format_version   u16
+//! endianness       u8       1 = little-endian
+//! layout           u8       0 = rows, 1 = columns, see `chunk::Layout`
+//! header_len       u32      total length of the header in bytes
+//! record_count     u64
+//! chunk_table      u64      offset of the chunk table, see `chunk.rs`
+//! record_size      u32      size of every record, 0 if variable
+//! min_record_size  u32      size of the slot area
+//! schema_hash      u64      see `Schema::fingerprint`
+//! created_at       u64      seconds since the Unix epoch
+//! compression      u8       0 = none, 1 = LZ4, 2 = zstd, see
+//!                           `compression::Compression`
+//! writer_version   u16 length + bytes
+//! field_count      u16, then per field: u16 length + name, u8 type tag
+//! ```
+//!
+//! All integers are little-endian.
+
+use crate::chunk::Layout;
+use crate::codec::{put_str16, ByteReader};
+use crate::compression::Compression;
+use crate::error::{Error, Result};
+use crate::record::{FieldType, Record, RecordSize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const MAGIC: [u8; 8] = *b"YOHSIN\0\0";
+pub const FORMAT_VERSION: u16 = 3;
+const LITTLE_ENDIAN: u8 = 1;
+
+/// Length of the part of the header that precedes `header_len` included,
+/// enough to know how many bytes to read for the whole header.
+pub const HEADER_PREFIX_LEN: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileHeader {
+    pub format_version: u16,
+    pub layout: Layout,
+    pub compression: Compression,
+    pub record_count: u64,
+    pub chunk_table_offset: u64,
+    pub record_size: RecordSize,
+    pub schema_hash: u64,
+    pub created_at: u64,
+    pub writer_version: String,
+    pub fields: Vec<(String, FieldType)>,
+}
+
+impl FileHeader {
+    /// Header describing a file of `record_count` records of type `T`, whose
+    /// chunk table starts at `chunk_table_offset`.
+    pub fn new<T: Record>(record_count: u64, chunk_table_offset: u64) -> Self {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        FileHeader {
+            format_version: FORMAT_VERSION,
+            layout: Layout::Rows,
+            compression: Compression::None,
+            record_count,
+            chunk_table_offset,
+            record_size: T::record_size(),
+            schema_hash: T::SCHEMA.fingerprint(),
+            created_at,
+            writer_version: env!("CARGO_PKG_VERSION").to_string(),
+            fields: T::SCHEMA
+                .fields
+                .iter()
+                .map(|field| (field.name.to_string(), field.ty))
+                .collect(),
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&MAGIC);
+        buf.extend_from_slice(&self.format_version.to_le_bytes());
+        buf.push(LITTLE_ENDIAN);
+        buf.push(self.layout.tag());
+        // Patched below once the length is known
+        buf.extend_from_slice(&0u32.to_le_bytes());
+        buf.extend_from_slice(&self.record_count.to_le_bytes());
+        buf.extend_from_slice(&self.chunk_table_offset.to_le_bytes());
+        let (record_size, min_record_size) = match self.record_size {
+            RecordSize::Fixed(size) => (size, size),
+            RecordSize::Variable { min } => (0, min),
+        };
+        buf.extend_from_slice(&(record_size as u32).to_le_bytes());
+        buf.extend_from_slice(&(min_record_size as u32).to_le_bytes());
+        buf.extend_from_slice(&self.schema_hash.to_le_bytes());
+        buf.extend_from_slice(&self.created_at.to_le_bytes());
+        buf.push(self.compression.tag());
+        put_str16(&mut buf, &self.writer_version);
+        buf.extend_from_slice(&(self.fields.len() as u16).to_le_bytes());
+        for (name, ty) in &self.fields {
+            put_str16(&mut buf, name);
+            buf.push(ty.tag());
+        }
+
+        let header_len = buf.len() as u32;
+        buf[12..16].copy_from_slice(&header_len.to_le_bytes());
+        buf
+    }
+
+    /// Length of the whole header, read from its first
+    /// [`HEADER_PREFIX_LEN`] bytes.
+    pub fn encoded_len(prefix: &[u8]) -> Result<usize> {
+        if prefix.len() >= 8 && prefix[..8] != MAGIC {
+            return Err(Error::BadMagic(prefix[..8].try_into().unwrap()));
+        }
+        let mut r = ByteReader::new(prefix);
+        r.bytes(12).ok_or(Error::Truncated { what: "header" })?;
+        let len = r.u32().ok_or(Error::Truncated { what: "header" })? as usize;
+        if len < HEADER_PREFIX_LEN {
+            return Err(Error::MalformedHeader("header length"));
+        }
+        Ok(len)
+    }
+
+    /// Parses a header from the start of `bytes`.
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut r = ByteReader::new(bytes);
+        let magic: [u8; 8] = r
+            .bytes(8)
+            .ok_or(Error::Truncated { what: "header" })?
+            .try_into()
+            .unwrap();
+        if magic != MAGIC {
+            return Err(Error::BadMagic(magic));
+        }
+        let format_version = r.u16().ok_or(Error::Truncated { what: "header" })?;
+        if format_version != FORMAT_VERSION {
+            return Err(Error::UnsupportedVersion(format_version));
+        }
+        let endianness = r.u8().ok_or(Error::Truncated { what: "header" })?;
+        if endianness != LITTLE_ENDIAN {
+            return Err(Error::EndiannessMismatch(endianness));
+        }
+        let layout = r.u8().ok_or(Error::Truncated { what: "header" })?;
+        let layout = Layout::from_tag(layout).ok_or(Error::MalformedHeader("layout"))?;
+        let header_len = r.u32().ok_or(Error::Truncated { what: "header" })? as usize;
+        if bytes.len() < header_len {
+            return Err(Error::Truncated { what: "header" });
+        }
+        let mut r = ByteReader::new(&bytes[..header_len]);
+        r.bytes(HEADER_PREFIX_LEN)
+            .ok_or(Error::Truncated { what: "header" })?;
+
+        let record_count = r.u64().ok_or(Error::Truncated { what: "header" })?;
+        let chunk_table_offset = r.u64().ok_or(Error::Truncated { what: "header" })?;
+        let record_size = r.u32().ok_or(Error::Truncated { what: "header" })? as usize;
+        let min_record_size = r.u32().ok_or(Error::Truncated { what: "header" })? as usize;
+        let record_size = if record_size == 0 {
+            RecordSize::Variable {
+                min: min_record_size,
+            }
+        } else {
+            RecordSize::Fixed(record_size)
+        };
+        let schema_hash = r.u64().ok_or(Error::Truncated { what: "header" })?;
+        let created_at = r.u64().ok_or(Error::Truncated { what: "header" })?;
+        let compression = r.u8().ok_or(Error::Truncated { what: "header" })?;
+        let compression =
+            Compression::from_tag(compression).ok_or(Error::MalformedHeader("compression"))?;
+        let writer_version = r
+            .str16()
+            .ok_or(Error::MalformedHeader("writer version"))?
+            .to_string();
+        let field_count = r.u16().ok_or(Error::Truncated { what: "header" })?;
+        let mut fields = Vec::with_capacity(field_count as usize);
+        for _ in 0..field_count {
+            let name = r
+                .str16()
+                .ok_or(Error::MalformedHeader("field name"))?
+                .to_string();
+            let tag = r.u8().ok_or(Error::Truncated { what: "header" })?;
+            let ty = FieldType::from_tag(tag).ok_or(Error::MalformedHeader("field type"))?;
+            fields.push((name, ty));
+        }
+
+        Ok(FileHeader {
+            format_version,
+            layout,
+            compression,
+            record_count,
+            chunk_table_offset,
+            record_size,
+            schema_hash,
+            created_at,
+            writer_version,
+            fields,
+        })
+    }
+
+    /// `record_count`, bounded by the number of records the bytes before the
+    /// chunk table could hold uncompressed, for sizing buffers without
+    /// trusting a damaged count.
+    pub fn capacity_hint(&self) -> usize {
+        let min_record_size = match self.record_size {
+            RecordSize::Fixed(size) => size,
+            RecordSize::Variable { min } => min,
+        };
+        let max_records = self.chunk_table_offset / min_record_size.max(1) as u64;
+        self.record_count.min(max_records) as usize
+    }
+
+    /// Checks that the file holds records of type `T`.
+    pub fn validate<T: Record>(&self) -> Result<()> {
+        let expected_hash = T::SCHEMA.fingerprint();
+        let fields_match = self.fields.len() == T::SCHEMA.fields.len()
+            && self
+                .fields
+                .iter()
+                .zip(T::SCHEMA.fields)
+                .all(|((name, ty), field)| name == field.name && *ty == field.ty);
+        if self.schema_hash != expected_hash || !fields_match {
+            return Err(Error::SchemaMismatch {
+                expected: T::SCHEMA.name,
+                expected_hash,
+                found_hash: self.schema_hash,
+                found_fields: self
+                    .fields
+                    .iter()
+                    .map(|(name, ty)| format!("{}: {}", name, ty.name()))
+                    .collect(),
+            });
+        }
+        if self.record_size != T::record_size() {
+            return Err(Error::RecordSizeMismatch {
+                expected: T::record_size(),
+                found: self.record_size,
+            });
+        }
+        Ok(())
+    }
+}
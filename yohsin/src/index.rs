@@ -0,0 +1,384 @@
+//! Secondary indexes mapping the value of one field to the records holding it.
+//!
+//! The index of field `<field>` of `<file>` lives next to it, at
+//! `<file>.<field>.idx`:
+//!
+//! ```text
+//! magic            8 bytes  b"YOHSIDX\0"
+//! version          u16
+//! field            u16 length + name, u8 type tag
+//! source           u32      table checksum of the indexed file
+//! entry_count      u64
+//! entries          per record: key offset u64, key length u32, record u64
+//! keys             the key bytes the entries point to
+//! crc              u32      CRC32C of everything above
+//! ```
+//!
+//! Entries are sorted by key, then by record, so the records holding a key
+//! are found by binary search. A key is the UTF-8 bytes of a string field, or
+//! the encoded bytes of a scalar one. `source` is the checksum that ends the
+//! chunk table of the indexed file, which covers its header and its layout:
+//! an index no longer matching its file (rewritten, appended to) is rejected
+//! rather than pointing at the wrong records.
+
+use crate::chunk::{chunk_record, chunk_records, ChunkEntry, ChunkTable};
+use crate::codec::{put_str16, ByteReader};
+use crate::decimal::parse_mantissa;
+use crate::error::{Error, Result};
+use crate::record::{Field, FieldType, FieldValue, Record};
+use crate::serialize::{read_chunk_table, read_header, read_verified_chunk};
+use rayon::slice::ParallelSliceMut;
+use std::ops::Range;
+use tokio::fs::File;
+use tokio::io::{AsyncWriteExt, BufReader};
+
+pub const INDEX_MAGIC: [u8; 8] = *b"YOHSIDX\0";
+pub const INDEX_VERSION: u16 = 1;
+
+/// Path of the index of `field` for the file at `file_path`.
+pub fn index_path(file_path: &str, field: &str) -> String {
+    format!("{}.{}.idx", file_path, field)
+}
+
+/// Checksum tying an index to the file whose header and chunk table are
+/// `header_bytes` and `table`.
+pub(crate) fn source_checksum(table: &ChunkTable, header_bytes: &[u8]) -> u32 {
+    let encoded = table.encode(header_bytes);
+    u32::from_le_bytes(encoded[encoded.len() - 4..].try_into().unwrap())
+}
+
+/// Looks up `name` in the schema of `T`, returning its position.
+pub(crate) fn field_position<T: Record>(name: &str) -> Result<usize> {
+    T::SCHEMA
+        .field_index(name)
+        .ok_or_else(|| Error::UnknownField {
+            schema: T::SCHEMA.name,
+            field: name.to_string(),
+        })
+}
+
+/// Looks up `name` in the schema of `T`, returning the field and the offset
+/// of its slot in an encoded record.
+pub(crate) fn field_of<T: Record>(name: &str) -> Result<(&'static Field, usize)> {
+    let idx = field_position::<T>(name)?;
+    let slot = T::SCHEMA.fields[..idx]
+        .iter()
+        .map(|field| field.ty.slot_len())
+        .sum();
+    Ok((&T::SCHEMA.fields[idx], slot))
+}
+
+/// Returns the key of an encoded record: the bytes of the field whose slot
+/// starts at `slot`.
+pub(crate) fn record_key(record: &[u8], slot: usize, ty: FieldType) -> Result<&[u8]> {
+    let corrupt = || Error::Corrupt("Indexed field lies outside of the record".into());
+    let bytes = record.get(slot..slot + ty.slot_len()).ok_or_else(corrupt)?;
+    if ty != FieldType::Str {
+        return Ok(bytes);
+    }
+    let offset = u32::from_le_bytes(bytes[..4].try_into().unwrap()) as usize;
+    let len = u32::from_le_bytes(bytes[4..].try_into().unwrap()) as usize;
+    record.get(offset..offset + len).ok_or_else(corrupt)
+}
+
+/// Converts a key given as text, as in a CSV file, to its indexed bytes.
+pub(crate) fn parse_key(field: &Field, key: &str) -> Result<Vec<u8>> {
+    let invalid = |reason: String| Error::InvalidKey {
+        field: field.name,
+        key: key.to_string(),
+        reason,
+    };
+    Ok(match field.ty {
+        FieldType::I32 => i32::parse(key).map_err(invalid)?.to_le_bytes().to_vec(),
+        FieldType::I64 => i64::parse(key).map_err(invalid)?.to_le_bytes().to_vec(),
+        FieldType::F64 => f64::parse(key).map_err(invalid)?.to_le_bytes().to_vec(),
+        FieldType::Bool => vec![bool::parse(key).map_err(invalid)? as u8],
+        FieldType::Str => key.as_bytes().to_vec(),
+        FieldType::Decimal(scale) => parse_mantissa(key, scale as u32)
+            .map_err(|e| invalid(e.to_string()))?
+            .to_le_bytes()
+            .to_vec(),
+    })
+}
+
+/// Collects the keys of one field, chunk by chunk.
+pub(crate) struct IndexBuilder {
+    field: &'static Field,
+    slot: usize,
+    keys: Vec<(Vec<u8>, u64)>,
+}
+
+impl IndexBuilder {
+    pub(crate) fn new<T: Record>(field: &str) -> Result<Self> {
+        let (field, slot) = field_of::<T>(field)?;
+        Ok(IndexBuilder {
+            field,
+            slot,
+            keys: Vec::new(),
+        })
+    }
+
+    /// Adds the keys of the records of `chunk`, located by `entry`.
+    pub(crate) fn add_chunk(&mut self, entry: &ChunkEntry, chunk: &[u8]) -> Result<()> {
+        for (record, bytes) in entry.records().zip(chunk_records(chunk)?) {
+            let key = record_key(bytes, self.slot, self.field.ty)?;
+            self.keys.push((key.to_vec(), record));
+        }
+        Ok(())
+    }
+
+    /// Sorts the keys and encodes the index of the file identified by
+    /// `source`.
+    fn encode(mut self, source: u32) -> Vec<u8> {
+        self.keys.par_sort_unstable();
+
+        let mut buf = Vec::new();
+        put_preamble(&mut buf, INDEX_MAGIC, self.field, source);
+        buf.extend_from_slice(&(self.keys.len() as u64).to_le_bytes());
+        let mut key_offset = 0u64;
+        for (key, record) in &self.keys {
+            buf.extend_from_slice(&key_offset.to_le_bytes());
+            buf.extend_from_slice(&(key.len() as u32).to_le_bytes());
+            buf.extend_from_slice(&record.to_le_bytes());
+            key_offset += key.len() as u64;
+        }
+        for (key, _) in &self.keys {
+            buf.extend_from_slice(key);
+        }
+        put_crc(&mut buf);
+        buf
+    }
+
+    /// Writes the index of the file identified by `source` to `path`.
+    pub(crate) async fn write(self, path: &str, source: u32) -> Result<()> {
+        write_aside(path, &self.encode(source)).await
+    }
+}
+
+/// Appends the preamble shared by the index files: `magic`, the version, the
+/// indexed field and the `source` checksum of the indexed file.
+pub(crate) fn put_preamble(buf: &mut Vec<u8>, magic: [u8; 8], field: &Field, source: u32) {
+    buf.extend_from_slice(&magic);
+    buf.extend_from_slice(&INDEX_VERSION.to_le_bytes());
+    put_str16(buf, field.name);
+    buf.push(field.ty.tag());
+    buf.extend_from_slice(&source.to_le_bytes());
+}
+
+/// Appends the CRC32C of `buf`.
+pub(crate) fn put_crc(buf: &mut Vec<u8>) {
+    let crc = crc32c::crc32c(buf);
+    buf.extend_from_slice(&crc.to_le_bytes());
+}
+
+/// Checks the checksum and the preamble of the index file `bytes`, read from
+/// `path`. Returns a reader of the bytes covered by the checksum, positioned
+/// after the preamble.
+pub(crate) fn read_preamble<'a>(
+    bytes: &'a [u8],
+    path: &str,
+    magic: [u8; 8],
+    field: &Field,
+    source: u32,
+) -> Result<ByteReader<'a>> {
+    let truncated = Error::Truncated { what: "index" };
+    let Some(covered) = bytes.len().checked_sub(4) else {
+        return Err(truncated);
+    };
+    let expected = u32::from_le_bytes(bytes[covered..].try_into().unwrap());
+    let found = crc32c::crc32c(&bytes[..covered]);
+    if found != expected {
+        return Err(Error::Corrupt(format!(
+            "Index checksum mismatch: expected {:#010x}, found {:#010x}",
+            expected, found
+        )));
+    }
+
+    let mut r = ByteReader::new(&bytes[..covered]);
+    let found_magic: [u8; 8] = r.bytes(8).ok_or(truncated)?.try_into().unwrap();
+    if found_magic != magic {
+        return Err(Error::BadMagic(found_magic));
+    }
+    let (Some(version), Some(name), Some(tag), Some(found_source)) =
+        (r.u16(), r.str16(), r.u8(), r.u32())
+    else {
+        return Err(Error::Truncated { what: "index" });
+    };
+    if version != INDEX_VERSION {
+        return Err(Error::UnsupportedVersion(version));
+    }
+    if name != field.name || FieldType::from_tag(tag) != Some(field.ty) {
+        return Err(Error::Corrupt(format!(
+            "Index of field `{}` found for `{}`",
+            name, field.name
+        )));
+    }
+    if found_source != source {
+        return Err(Error::StaleIndex {
+            path: path.to_string(),
+        });
+    }
+    Ok(r)
+}
+
+/// Writes `bytes` next to `path`, then renames them over it, so that a reader
+/// never sees a partial index.
+pub(crate) async fn write_aside(path: &str, bytes: &[u8]) -> Result<()> {
+    let tmp = format!("{}.tmp", path);
+    let mut file = File::create(&tmp).await?;
+    file.write_all(bytes).await?;
+    file.sync_all().await?;
+    tokio::fs::rename(&tmp, path).await?;
+    Ok(())
+}
+
+/// Builds (or rebuilds) the index of `field` for the file at `file_path`, for
+/// instance after records were appended to it. Every chunk is read and
+/// checked against its checksum.
+pub async fn build_index<T: Record>(file_path: &str, field: &str) -> Result<()> {
+    let mut builder = IndexBuilder::new::<T>(field)?;
+    let mut file = BufReader::new(File::open(file_path).await?);
+    let (header, header_bytes) = read_header::<T>(&mut file).await?;
+    let table = read_chunk_table(&mut file, &header, &header_bytes).await?;
+    for (idx, entry) in table.entries.iter().enumerate() {
+        let chunk = read_verified_chunk(&mut file, &header, &table, idx).await?;
+        builder.add_chunk(entry, &chunk)?;
+    }
+    builder
+        .write(
+            &index_path(file_path, field),
+            source_checksum(&table, &header_bytes),
+        )
+        .await
+}
+
+/// A loaded index, checked against its checksum. Kept around, it serves any
+/// number of lookups while its file does not change.
+#[derive(Debug)]
+pub struct KeyIndex {
+    field: &'static Field,
+    /// Table checksum of the indexed file.
+    source: u32,
+    /// Position of each key in `keys`, and its record.
+    entries: Vec<(Range<usize>, u64)>,
+    keys: Vec<u8>,
+}
+
+impl KeyIndex {
+    /// Loads the index of `field` at `path`, which must have been built for
+    /// the file identified by `source`.
+    async fn load<T: Record>(path: &str, field: &str, source: u32) -> Result<Self> {
+        let (field, _) = field_of::<T>(field)?;
+        let bytes = tokio::fs::read(path).await?;
+        let mut r = read_preamble(&bytes, path, INDEX_MAGIC, field, source)?;
+        let count = r.u64().ok_or(Error::Truncated { what: "index" })?;
+        let covered = bytes.len() - 4;
+
+        let mut raw = Vec::with_capacity((count as usize).min(covered / 20));
+        for _ in 0..count {
+            let (Some(offset), Some(len), Some(record)) = (r.u64(), r.u32(), r.u64()) else {
+                return Err(Error::Truncated { what: "index" });
+            };
+            raw.push((offset as usize, len as usize, record));
+        }
+        let keys = bytes[r.position()..covered].to_vec();
+        let entries = raw
+            .into_iter()
+            .map(|(offset, len, record)| match offset.checked_add(len) {
+                Some(end) if end <= keys.len() => Ok((offset..end, record)),
+                _ => Err(Error::Corrupt("Index key lies outside of the index".into())),
+            })
+            .collect::<Result<_>>()?;
+        Ok(KeyIndex {
+            field,
+            source,
+            entries,
+            keys,
+        })
+    }
+
+    /// Opens the index of `field` for the file at `file_path`, checking that
+    /// it matches the current content of the file.
+    pub async fn open<T: Record>(file_path: &str, field: &str) -> Result<Self> {
+        let mut file = BufReader::new(File::open(file_path).await?);
+        let (header, header_bytes) = read_header::<T>(&mut file).await?;
+        let table = read_chunk_table(&mut file, &header, &header_bytes).await?;
+        let source = source_checksum(&table, &header_bytes);
+        Self::load::<T>(&index_path(file_path, field), field, source).await
+    }
+
+    /// Number of records indexed.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Indices of the records whose field equals `key`, in ascending order.
+    /// `key` is written as in a CSV file.
+    pub fn records(&self, key: &str) -> Result<Vec<u64>> {
+        let key = parse_key(self.field, key)?;
+        let key_of = |(range, _): &(Range<usize>, u64)| &self.keys[range.clone()];
+        let first = self
+            .entries
+            .partition_point(|entry| key_of(entry) < key.as_slice());
+        let last = self
+            .entries
+            .partition_point(|entry| key_of(entry) <= key.as_slice());
+        Ok(self.entries[first..last]
+            .iter()
+            .map(|&(_, record)| record)
+            .collect())
+    }
+
+    /// Reads the records of the file at `file_path`, which the index was
+    /// opened for, whose field equals `key`, in file order.
+    ///
+    /// Only the header, the chunk table and the chunks holding the matching
+    /// records are read; each of these chunks is checked against its
+    /// checksum. A file changed since the index was opened is reported as
+    /// [`Error::StaleIndex`].
+    pub async fn lookup<T: Record>(&self, file_path: &str, key: &str) -> Result<Vec<T>> {
+        let mut file = BufReader::new(File::open(file_path).await?);
+        let (header, header_bytes) = read_header::<T>(&mut file).await?;
+        let table = read_chunk_table(&mut file, &header, &header_bytes).await?;
+        if source_checksum(&table, &header_bytes) != self.source {
+            return Err(Error::StaleIndex {
+                path: index_path(file_path, self.field.name),
+            });
+        }
+
+        let mut records = Vec::new();
+        let mut current: Option<(usize, Vec<u8>)> = None;
+        for record in self.records(key)? {
+            let idx = table.chunks_for(&(record..record + 1)).start;
+            let entry = table.entries.get(idx).ok_or_else(|| {
+                Error::Corrupt(format!("Index points at missing record {}", record))
+            })?;
+            if current.as_ref().map(|(loaded, _)| *loaded) != Some(idx) {
+                current = Some((
+                    idx,
+                    read_verified_chunk(&mut file, &header, &table, idx).await?,
+                ));
+            }
+            let (_, chunk) = current.as_ref().unwrap();
+            let bytes = chunk_record(chunk, (record - entry.first_record) as usize)?;
+            records.push(T::decode(bytes)?);
+        }
+        Ok(records)
+    }
+}
+
+/// Reads the records of the file at `file_path` whose `field` equals `key`,
+/// using the index of `field`, in file order.
+///
+/// The whole index is loaded and checked for this lookup alone: to look up
+/// several keys, open a [`KeyIndex`] once and use [`KeyIndex::lookup`].
+pub async fn lookup_by_key<T: Record>(file_path: &str, field: &str, key: &str) -> Result<Vec<T>> {
+    KeyIndex::open::<T>(file_path, field)
+        .await?
+        .lookup(file_path, key)
+        .await
+}
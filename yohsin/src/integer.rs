@@ -0,0 +1,160 @@
+//! Compact encodings of integer columns, see [`crate::columnar`].
+//!
+//! ```text
+//! delta               zig-zag varint of the first value, then of the
+//!                     difference between each value and the previous one
+//! frame of reference  min i64, width u8, then each value minus min on
+//!                     `width` bits, least significant bit first
+//! ```
+//!
+//! Delta suits monotonic values such as timestamps or sequential ids,
+//! frame of reference values within a narrow range such as quantities.
+//! Arithmetic wraps, so any `i64` round-trips.
+
+use crate::error::{Error, Result};
+
+/// Maps signed integers to unsigned ones, small in magnitude to small:
+/// 0, -1, 1, -2, ... become 0, 1, 2, 3, ...
+pub fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+pub fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+/// Appends `value` as a LEB128 varint: 7 bits per byte, high bit set on
+/// every byte but the last.
+pub fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+/// Reads a varint from the start of `bytes`, advancing past it.
+pub fn get_varint(bytes: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first()?;
+        *bytes = rest;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Appends the delta encoding of `values`.
+pub fn encode_delta(buf: &mut Vec<u8>, values: &[i64]) {
+    let mut previous = 0i64;
+    for &value in values {
+        put_varint(buf, zigzag(value.wrapping_sub(previous)));
+        previous = value;
+    }
+}
+
+/// Decodes `count` delta-encoded values, which must take all of `bytes`.
+pub fn decode_delta(mut bytes: &[u8], count: usize) -> Result<Vec<i64>> {
+    // Every value takes at least a byte
+    let mut values = Vec::with_capacity(count.min(bytes.len()));
+    let mut previous = 0i64;
+    for _ in 0..count {
+        let delta = get_varint(&mut bytes).ok_or(Error::Truncated { what: "column" })?;
+        previous = previous.wrapping_add(unzigzag(delta));
+        values.push(previous);
+    }
+    if !bytes.is_empty() {
+        return Err(Error::Corrupt(format!(
+            "{} bytes after {} delta-encoded values",
+            bytes.len(),
+            count
+        )));
+    }
+    Ok(values)
+}
+
+/// Number of bits needed for the offsets of `values` from their minimum.
+fn width_of(values: &[i64]) -> (i64, u32) {
+    let min = values.iter().copied().min().unwrap_or_default();
+    let max = values.iter().copied().max().unwrap_or_default();
+    (min, 64 - (max.wrapping_sub(min) as u64).leading_zeros())
+}
+
+/// Length of the frame of reference encoding of `values`, without encoding
+/// them.
+pub fn frame_of_reference_len(values: &[i64]) -> usize {
+    let (_, width) = width_of(values);
+    9 + (values.len() * width as usize).div_ceil(8)
+}
+
+/// Appends the frame of reference encoding of `values`.
+pub fn encode_frame_of_reference(buf: &mut Vec<u8>, values: &[i64]) {
+    let (min, width) = width_of(values);
+    buf.extend_from_slice(&min.to_le_bytes());
+    buf.push(width as u8);
+
+    // Fewer than 8 bits are pending before each value is added
+    let mut pending = 0u128;
+    let mut bits = 0;
+    for &value in values {
+        pending |= (value.wrapping_sub(min) as u64 as u128) << bits;
+        bits += width;
+        while bits >= 8 {
+            buf.push(pending as u8);
+            pending >>= 8;
+            bits -= 8;
+        }
+    }
+    if bits > 0 {
+        buf.push(pending as u8);
+    }
+}
+
+/// Decodes `count` values encoded with a frame of reference, which must take
+/// all of `bytes`.
+pub fn decode_frame_of_reference(bytes: &[u8], count: usize) -> Result<Vec<i64>> {
+    if bytes.len() < 9 {
+        return Err(Error::Truncated { what: "column" });
+    }
+    let min = i64::from_le_bytes(bytes[..8].try_into().unwrap());
+    let width = bytes[8] as u32;
+    if width > 64 {
+        return Err(Error::Corrupt(format!("Bit width {} exceeds 64", width)));
+    }
+    let packed = &bytes[9..];
+    let expected = (count * width as usize).div_ceil(8);
+    if packed.len() != expected {
+        return Err(Error::Corrupt(format!(
+            "{} values of {} bits take {} bytes, found {}",
+            count,
+            width,
+            expected,
+            packed.len()
+        )));
+    }
+
+    let mask = match width {
+        64 => u64::MAX,
+        _ => (1u64 << width) - 1,
+    };
+    // Values of 0 bits take no bytes: their count is not bounded by the
+    // length checked above
+    let mut values = Vec::with_capacity(count.min(8 * packed.len().max(1)));
+    let mut packed = packed.iter();
+    let mut pending = 0u128;
+    let mut bits = 0;
+    for _ in 0..count {
+        while bits < width {
+            // Length checked above
+            pending |= (*packed.next().unwrap() as u128) << bits;
+            bits += 8;
+        }
+        values.push(min.wrapping_add((pending as u64 & mask) as i64));
+        pending >>= width;
+        bits -= width;
+    }
+    Ok(values)
+}
@@ -0,0 +1,221 @@
+//! Write-ahead journal making `serialize_to_file` resumable.
+//!
+//! The journal is a text file with one entry per line:
+//!
+//! ```text
+//! begin <layout fingerprint> <created_at>
+//! chunk <index>
+//! chunk <index>
+//! ```
+//!
+//! A [`RecordWriter`](crate::writer::RecordWriter) does not know the layout
+//! up front, so its journal records every chunk as it is appended instead:
+//!
+//! ```text
+//! stream <schema fingerprint> <chunk_records> <created_at> <layout> <compression>
+//! entry <offset> <len> <first_record> <record_count> <crc>
+//! ```
+//!
+//! A `chunk` or `entry` line is only appended once the chunk has been written
+//! and the data file synced, so every chunk listed in the journal is durable.
+//! An empty (or missing) journal means that no serialization is in progress.
+
+use crate::chunk::{ChunkEntry, ChunkTable, Layout};
+use crate::codec::{fnv1a, FNV_OFFSET};
+use crate::compression::Compression;
+use crate::header::FileHeader;
+use std::collections::BTreeSet;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+
+/// Progress of an interrupted serialization, as recorded in its journal.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JournalState {
+    /// Fingerprint of the layout being written, see [`layout_fingerprint`].
+    pub layout: u64,
+    /// Creation time to put in the header, so that a resumed file is
+    /// identical to the one an uninterrupted run would have produced.
+    pub created_at: u64,
+    /// Chunks known to be durable.
+    pub completed: BTreeSet<usize>,
+}
+
+impl JournalState {
+    fn parse(content: &str) -> Option<Self> {
+        let mut lines = content.lines();
+        let mut begin = lines.next()?.split(' ');
+        if begin.next()? != "begin" {
+            return None;
+        }
+        let layout = u64::from_str_radix(begin.next()?, 16).ok()?;
+        let created_at = begin.next()?.parse().ok()?;
+
+        // A torn last line is simply ignored: its chunk will be rewritten
+        let completed = lines
+            .filter_map(|line| line.strip_prefix("chunk ")?.parse().ok())
+            .collect();
+
+        Some(JournalState {
+            layout,
+            created_at,
+            completed,
+        })
+    }
+}
+
+/// Progress of an interrupted [`RecordWriter`](crate::writer::RecordWriter),
+/// as recorded in its journal.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StreamJournalState {
+    pub schema_hash: u64,
+    pub chunk_records: usize,
+    pub created_at: u64,
+    pub layout: Layout,
+    pub compression: Compression,
+    /// Chunks known to be durable, in file order.
+    pub entries: Vec<ChunkEntry>,
+}
+
+impl StreamJournalState {
+    fn parse(content: &str) -> Option<Self> {
+        let mut lines = content.lines();
+        let mut stream = lines.next()?.split(' ');
+        if stream.next()? != "stream" {
+            return None;
+        }
+        let schema_hash = u64::from_str_radix(stream.next()?, 16).ok()?;
+        let chunk_records = stream.next()?.parse().ok()?;
+        let created_at = stream.next()?.parse().ok()?;
+        let layout = Layout::from_tag(stream.next()?.parse().ok()?)?;
+        let compression = Compression::from_tag(stream.next()?.parse().ok()?)?;
+
+        // Entries are appended in order; stop at a torn last line
+        let mut entries: Vec<ChunkEntry> = Vec::new();
+        for line in lines {
+            let Some(entry) = parse_entry(line) else {
+                break;
+            };
+            let next_record = entries.last().map_or(0, |last| last.records().end);
+            if entry.first_record != next_record {
+                break;
+            }
+            entries.push(entry);
+        }
+
+        Some(StreamJournalState {
+            schema_hash,
+            chunk_records,
+            created_at,
+            layout,
+            compression,
+            entries,
+        })
+    }
+}
+
+fn parse_entry(line: &str) -> Option<ChunkEntry> {
+    let mut parts = line.strip_prefix("entry ")?.split(' ');
+    let entry = ChunkEntry {
+        offset: parts.next()?.parse().ok()?,
+        len: parts.next()?.parse().ok()?,
+        first_record: parts.next()?.parse().ok()?,
+        record_count: parts.next()?.parse().ok()?,
+        crc: u32::from_str_radix(parts.next()?, 16).ok()?,
+    };
+    parts.next().is_none().then_some(entry)
+}
+
+/// Fingerprint of everything that determines where chunks land in the file:
+/// the header (without its creation time) and the chunk table.
+pub fn layout_fingerprint(header: &FileHeader, table: &ChunkTable) -> u64 {
+    let mut header = header.clone();
+    header.created_at = 0;
+    let header = header.encode();
+    let hash = fnv1a(FNV_OFFSET, &header);
+    fnv1a(hash, &table.encode(&header))
+}
+
+pub struct Journal {
+    file: File,
+}
+
+impl Journal {
+    /// Reads the journal at `path`, if it records a serialization in progress.
+    pub async fn load(path: &str) -> Option<JournalState> {
+        let content = tokio::fs::read_to_string(path).await.ok()?;
+        JournalState::parse(&content)
+    }
+
+    /// Reads the journal at `path`, if it records a
+    /// [`RecordWriter`](crate::writer::RecordWriter) in progress.
+    pub async fn load_stream(path: &str) -> Option<StreamJournalState> {
+        let content = tokio::fs::read_to_string(path).await.ok()?;
+        StreamJournalState::parse(&content)
+    }
+
+    /// Starts a new journal at `path`, discarding any previous content.
+    pub async fn begin(path: &str, layout: u64, created_at: u64) -> std::io::Result<Self> {
+        Self::create(path, format!("begin {:016x} {}\n", layout, created_at)).await
+    }
+
+    /// Starts a new journal for a [`RecordWriter`](crate::writer::RecordWriter)
+    /// at `path`, discarding any previous content.
+    pub async fn begin_stream(
+        path: &str,
+        schema_hash: u64,
+        chunk_records: usize,
+        created_at: u64,
+        layout: Layout,
+        compression: Compression,
+    ) -> std::io::Result<Self> {
+        let line = format!(
+            "stream {:016x} {} {} {} {}\n",
+            schema_hash,
+            chunk_records,
+            created_at,
+            layout.tag(),
+            compression.tag()
+        );
+        Self::create(path, line).await
+    }
+
+    async fn create(path: &str, first_line: String) -> std::io::Result<Self> {
+        let mut file = File::create(path).await?;
+        file.write_all(first_line.as_bytes()).await?;
+        file.sync_all().await?;
+        Ok(Journal { file })
+    }
+
+    /// Records `chunks` as durable. The caller must have synced them to the
+    /// data file first.
+    pub async fn complete(&mut self, chunks: &[usize]) -> std::io::Result<()> {
+        let lines: String = chunks
+            .iter()
+            .map(|idx| format!("chunk {}\n", idx))
+            .collect();
+        self.file.write_all(lines.as_bytes()).await?;
+        self.file.sync_data().await
+    }
+
+    /// Records appended chunks as durable. The caller must have synced them
+    /// to the data file first.
+    pub async fn append(&mut self, entries: &[ChunkEntry]) -> std::io::Result<()> {
+        let lines: String = entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    "entry {} {} {} {} {:08x}\n",
+                    entry.offset, entry.len, entry.first_record, entry.record_count, entry.crc
+                )
+            })
+            .collect();
+        self.file.write_all(lines.as_bytes()).await?;
+        self.file.sync_data().await
+    }
+
+    /// Marks the serialization as finished by emptying the journal.
+    pub async fn clear(&mut self) -> std::io::Result<()> {
+        self.file.set_len(0).await?;
+        self.file.sync_all().await
+    }
+}
@@ -1,2 +1,30 @@
+// Lets `#[derive(Record)]` refer to `::yohsin` from inside this crate too.
+extern crate self as yohsin;
+
+pub mod chunk;
+pub mod codec;
+pub mod columnar;
+pub mod compact;
+pub mod compression;
+pub mod csv;
+pub mod decimal;
+pub mod error;
+#[cfg(feature = "failpoints")]
+pub mod failpoint;
+pub mod filter;
+pub mod header;
+pub mod index;
+pub mod integer;
+pub mod journal;
+pub mod mapped;
 pub mod order_struct;
+pub mod project;
+pub mod record;
+pub mod segment;
 pub mod serialize;
+pub mod time_index;
+pub mod writer;
+
+pub use error::{Error, Result};
+pub use record::Record;
+pub use yohsin_derive::Record;
@@ -12,8 +12,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Serialize data to file
     let file_path = Arc::new("dump.bin".to_string());
-    let memo_file = Arc::new("memo.txt".to_string());
-    serialize_to_file(original_data, file_path.clone(), memo_file.clone()).await?;
+    let journal_file = Arc::new("journal.txt".to_string());
+    serialize_to_file(original_data, file_path.clone(), journal_file).await?;
 
     // Calculate elapsed time
     println!("Time elapsed (serialize+dump) : {:?}", start.elapsed());
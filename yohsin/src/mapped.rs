@@ -0,0 +1,219 @@
+//! Zero-copy reads through a memory mapping of the file.
+//!
+//! [`MappedFile`] hands out [`Record::View`]s whose strings point into the
+//! mapping, so reading a record allocates nothing. Each chunk is checked
+//! against its checksum the first time one of its records is read.
+//!
+//! The chunks of a columnar or compressed file are not laid out as records:
+//! each is decompressed and rewritten in the row layout once, the first time
+//! it is read, and views borrow from that copy instead. Such copies are kept
+//! until [`MappedFile::evict`]; [`MappedFile::chunks`] reads a large file one
+//! chunk at a time instead, each copy dropped with its [`MappedChunk`].
+
+use crate::chunk::{chunk_as_rows, chunk_record, ChunkTable, Layout};
+use crate::compression::Compression;
+use crate::error::{Error, Result};
+use crate::header::{FileHeader, HEADER_PREFIX_LEN};
+use crate::record::Record;
+use memmap2::Mmap;
+use std::borrow::Cow;
+use std::fs::File;
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+/// A file of `T` records, mapped in memory.
+pub struct MappedFile<T> {
+    map: Mmap,
+    header: FileHeader,
+    table: ChunkTable,
+    verified: Box<[AtomicBool]>,
+    /// Chunks of a columnar or compressed file, in the row layout.
+    rows: Box<[OnceLock<Vec<u8>>]>,
+    _records: PhantomData<fn() -> T>,
+}
+
+impl<T: Record> MappedFile<T> {
+    /// Maps `file_path` and checks its header and chunk table.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified, by this process or another, until the
+    /// `MappedFile` is dropped: the views it hands out borrow the mapped
+    /// bytes, which would change under them. That rules out appending to it
+    /// (see [`crate::serialize::append_to_file`], which rewrites its header
+    /// and chunk table in place), overwriting it or truncating it.
+    pub unsafe fn open(file_path: &str) -> Result<Self> {
+        let file = File::open(file_path)?;
+        // SAFETY: the caller does not modify the file while it is mapped, and
+        // every read goes through bounds-checked slices of the mapping.
+        let map = unsafe { Mmap::map(&file)? };
+
+        let prefix = map.get(..HEADER_PREFIX_LEN).unwrap_or(&map);
+        let header_len = FileHeader::encoded_len(prefix)?;
+        let header_bytes = map
+            .get(..header_len)
+            .ok_or(Error::Truncated { what: "header" })?;
+        let header = FileHeader::decode(header_bytes)?;
+        header.validate::<T>()?;
+
+        let table_bytes =
+            map.get(header.chunk_table_offset as usize..)
+                .ok_or(Error::Truncated {
+                    what: "chunk table",
+                })?;
+        let table = ChunkTable::decode(table_bytes, header_bytes)?;
+        table.check_layout(&header, header_len)?;
+
+        let verified = table
+            .entries
+            .iter()
+            .map(|_| AtomicBool::new(false))
+            .collect();
+        let rows = table.entries.iter().map(|_| OnceLock::new()).collect();
+        Ok(MappedFile {
+            map,
+            header,
+            table,
+            verified,
+            rows,
+            _records: PhantomData,
+        })
+    }
+
+    pub fn header(&self) -> &FileHeader {
+        &self.header
+    }
+
+    pub fn len(&self) -> usize {
+        self.header.record_count as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Record number `idx`, borrowed from the mapping.
+    pub fn get(&self, idx: usize) -> Result<T::View<'_>> {
+        if idx >= self.len() {
+            return Err(Error::RangeOutOfBounds {
+                start: idx,
+                end: idx + 1,
+                len: self.len(),
+            });
+        }
+        let wanted = idx as u64..idx as u64 + 1;
+        let chunk_idx = self.table.chunks_for(&wanted).start;
+        let chunk = self.chunk(chunk_idx)?;
+        let first = self.table.entries[chunk_idx].first_record;
+        T::view(chunk_record(chunk, (idx as u64 - first) as usize)?)
+    }
+
+    /// The records at positions `range`, borrowed from the mapping.
+    pub fn range(
+        &self,
+        range: Range<usize>,
+    ) -> Result<impl Iterator<Item = Result<T::View<'_>>> + '_> {
+        if range.start > range.end || range.end > self.len() {
+            return Err(Error::RangeOutOfBounds {
+                start: range.start,
+                end: range.end,
+                len: self.len(),
+            });
+        }
+        Ok(range.map(move |idx| self.get(idx)))
+    }
+
+    /// Every record of the file, borrowed from the mapping.
+    pub fn iter(&self) -> impl Iterator<Item = Result<T::View<'_>>> + '_ {
+        (0..self.len()).map(move |idx| self.get(idx))
+    }
+
+    /// The chunks of the file, in record order, each in the row layout.
+    pub fn chunks(&self) -> impl Iterator<Item = Result<MappedChunk<'_, T>>> + '_ {
+        (0..self.table.entries.len()).map(move |idx| {
+            let rows = match self.rows[idx].get() {
+                Some(rows) => Cow::Borrowed(rows.as_slice()),
+                None => chunk_as_rows(&self.header, idx, self.stored_chunk(idx)?)?,
+            };
+            Ok(MappedChunk {
+                rows,
+                len: self.table.entries[idx].record_count as usize,
+                _records: PhantomData,
+            })
+        })
+    }
+
+    /// Drops the chunks of a columnar or compressed file rewritten in the row
+    /// layout so far, which every chunk read through [`MappedFile::get`],
+    /// [`MappedFile::range`] or [`MappedFile::iter`] is until then.
+    pub fn evict(&mut self) {
+        for rows in self.rows.iter_mut() {
+            rows.take();
+        }
+    }
+
+    /// Bytes of chunk number `idx` in the row layout, checked against its
+    /// checksum once.
+    fn chunk(&self, idx: usize) -> Result<&[u8]> {
+        if let Some(rows) = self.rows[idx].get() {
+            return Ok(rows);
+        }
+        let bytes = self.stored_chunk(idx)?;
+        if self.header.layout == Layout::Rows && self.header.compression == Compression::None {
+            return Ok(bytes);
+        }
+        let rows = chunk_as_rows(&self.header, idx, bytes)?.into_owned();
+        // Another thread may have converted the chunk meanwhile
+        Ok(self.rows[idx].get_or_init(|| rows))
+    }
+
+    /// Bytes of chunk number `idx` as stored, checked against its checksum
+    /// once.
+    fn stored_chunk(&self, idx: usize) -> Result<&[u8]> {
+        let entry = &self.table.entries[idx];
+        // In bounds: checked by `ChunkTable::check_layout`
+        let bytes = &self.map[entry.offset as usize..(entry.offset + entry.len) as usize];
+        if !self.verified[idx].load(Ordering::Relaxed) {
+            entry.verify(idx, bytes)?;
+            self.verified[idx].store(true, Ordering::Relaxed);
+        }
+        Ok(bytes)
+    }
+}
+
+/// The records of one chunk of a [`MappedFile`], borrowed from the mapping
+/// or, for a columnar or compressed file, from their copy in the row layout.
+pub struct MappedChunk<'a, T> {
+    rows: Cow<'a, [u8]>,
+    len: usize,
+    _records: PhantomData<fn() -> T>,
+}
+
+impl<T: Record> MappedChunk<'_, T> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Record number `idx` of the chunk.
+    pub fn get(&self, idx: usize) -> Result<T::View<'_>> {
+        if idx >= self.len {
+            return Err(Error::RangeOutOfBounds {
+                start: idx,
+                end: idx + 1,
+                len: self.len,
+            });
+        }
+        T::view(chunk_record(&self.rows, idx)?)
+    }
+
+    /// Every record of the chunk.
+    pub fn iter(&self) -> impl Iterator<Item = Result<T::View<'_>>> + '_ {
+        (0..self.len).map(move |idx| self.get(idx))
+    }
+}
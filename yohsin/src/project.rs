@@ -0,0 +1,142 @@
+//! Reading a subset of the fields of a record.
+
+use crate::chunk::{chunk_records, Layout};
+use crate::codec::RowWriter;
+use crate::columnar::{columns_of, read_columns, read_directory, Column};
+use crate::error::{Error, Result};
+use crate::index::{field_of, field_position, record_key};
+use crate::record::{FieldType, Record};
+use crate::serialize::{read_chunk_table, read_header, read_verified_chunk};
+use std::marker::PhantomData;
+use std::sync::Arc;
+use tokio::fs::File;
+use tokio::io::BufReader;
+
+/// How to build a record of type `P` from the encoded records of type `T`:
+/// the position, slot and type, in `T`, of each field of `P`.
+pub(crate) struct Projection<T, P> {
+    fields: Vec<(usize, usize, FieldType)>,
+    _types: PhantomData<fn(T) -> P>,
+}
+
+impl<T: Record, P: Record> Projection<T, P> {
+    /// Matches the fields of `P` with the fields of `T` of the same name,
+    /// which must have the same type.
+    pub(crate) fn new() -> Result<Self> {
+        let fields = P::SCHEMA
+            .fields
+            .iter()
+            .map(|field| {
+                let (source, slot) = field_of::<T>(field.name)?;
+                if source.ty != field.ty {
+                    return Err(Error::UnsupportedFieldType {
+                        field: field.name,
+                        ty: field.ty,
+                    });
+                }
+                Ok((field_position::<T>(field.name)?, slot, field.ty))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Projection {
+            fields,
+            _types: PhantomData,
+        })
+    }
+
+    /// Positions, in `T`, of the fields of `P`.
+    pub(crate) fn positions(&self) -> Vec<usize> {
+        self.fields
+            .iter()
+            .map(|&(position, _, _)| position)
+            .collect()
+    }
+
+    /// Builds the projection of the encoded record `bytes`, reading only the
+    /// fields of `P`. `buf` is scratch space.
+    pub(crate) fn project(&self, bytes: &[u8], buf: &mut Vec<u8>) -> Result<P> {
+        self.build(buf, |_, slot, ty| record_key(bytes, slot, ty))
+    }
+
+    /// Builds the projection of record number `row` of a row group whose
+    /// columns are `columns`, which must include the fields of `P`.
+    pub(crate) fn project_columns(
+        &self,
+        columns: &[Option<Column>],
+        row: usize,
+        buf: &mut Vec<u8>,
+    ) -> Result<P> {
+        // Every column of the projection was read
+        self.build(buf, |position, _, _| {
+            columns[position].as_ref().unwrap().value(row)
+        })
+    }
+
+    /// Builds a `P` from the encoded value of each of its fields, given by
+    /// `value(position, slot, type)`.
+    fn build<'a>(
+        &self,
+        buf: &mut Vec<u8>,
+        value: impl Fn(usize, usize, FieldType) -> Result<&'a [u8]>,
+    ) -> Result<P> {
+        buf.clear();
+        let mut w = RowWriter::new(buf, P::SCHEMA.fixed_len());
+        for &(position, slot, ty) in &self.fields {
+            w.put_encoded(ty, value(position, slot, ty)?);
+        }
+        P::decode(buf)
+    }
+}
+
+/// Deserializes every record of a file of `T` records as a `P`, a record
+/// holding some of the fields of `T`:
+///
+/// ```ignore
+/// #[derive(Record)]
+/// struct Fill {
+///     orderid: String,
+///     qtyexec: i64,
+///     priceexec: Price,
+/// }
+///
+/// let fills = deserialize_projected::<DailyBlotterData, Fill>(path).await?;
+/// ```
+///
+/// Fields are matched by name and must have the same type in both records.
+/// Only the fields of `P` are read from each encoded record; in a columnar
+/// file, only their columns are read from disk, each checked against its
+/// checksum.
+pub async fn deserialize_projected<T, P>(file_path: Arc<String>) -> Result<Arc<[P]>>
+where
+    T: Record,
+    P: Record,
+{
+    let projection = Projection::<T, P>::new()?;
+    let mut file = BufReader::new(File::open(&*file_path).await?);
+    let (header, header_bytes) = read_header::<T>(&mut file).await?;
+    let table = read_chunk_table(&mut file, &header, &header_bytes).await?;
+
+    let mut retrieved_data = Vec::with_capacity(header.capacity_hint());
+    let mut buf = Vec::new();
+    if header.layout == Layout::Columns {
+        let positions = projection.positions();
+        for idx in 0..table.entries.len() {
+            let directory = read_directory(&mut file, &header, &table, idx).await?;
+            let bytes =
+                read_columns(&mut file, &header, &table, idx, &directory, &positions).await?;
+            let columns = columns_of(&header, &directory, &bytes)?;
+            for row in 0..directory.record_count as usize {
+                retrieved_data.push(projection.project_columns(&columns, row, &mut buf)?);
+            }
+        }
+        return Ok(Arc::from(retrieved_data));
+    }
+
+    for idx in 0..table.entries.len() {
+        let chunk = read_verified_chunk(&mut file, &header, &table, idx).await?;
+        for bytes in chunk_records(&chunk)? {
+            retrieved_data.push(projection.project(bytes, &mut buf)?);
+        }
+    }
+
+    Ok(Arc::from(retrieved_data))
+}
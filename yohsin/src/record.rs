@@ -0,0 +1,259 @@
+//! The contract between a type and the storage engine.
+//!
+//! Only types implementing [`Record`] can be written to or read from a file,
+//! which rules out persisting arbitrary memory (pointers, padding, types with
+//! `Drop` glue) by reinterpreting its bytes.
+
+use crate::codec::{fnv1a, RowReader, RowWriter, FNV_OFFSET, STR_SLOT};
+use crate::decimal::MAX_SCALE;
+use crate::error::Result;
+use std::str::FromStr;
+
+/// Tag of [`FieldType::Decimal`] at scale 0, followed by those of every
+/// other scale up to [`MAX_SCALE`].
+const DECIMAL_TAG: u8 = 0x10;
+const MAX_DECIMAL_TAG: u8 = DECIMAL_TAG + MAX_SCALE as u8;
+
+/// Type of a single field as laid out by [`crate::codec::RowWriter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    I32,
+    I64,
+    F64,
+    Bool,
+    Str,
+    /// A [`crate::decimal::Decimal`] with this many digits after the point,
+    /// stored as a scaled `i64`.
+    Decimal(u8),
+}
+
+impl FieldType {
+    /// Number of bytes the field takes in the slot area of a record.
+    pub const fn slot_len(self) -> usize {
+        match self {
+            FieldType::I32 => 4,
+            FieldType::I64 | FieldType::F64 | FieldType::Decimal(_) => 8,
+            FieldType::Bool => 1,
+            FieldType::Str => STR_SLOT,
+        }
+    }
+
+    /// Stable identifier of the type in file headers.
+    pub const fn tag(self) -> u8 {
+        match self {
+            FieldType::I32 => 1,
+            FieldType::I64 => 2,
+            FieldType::F64 => 3,
+            FieldType::Bool => 4,
+            FieldType::Str => 5,
+            FieldType::Decimal(scale) => DECIMAL_TAG + scale,
+        }
+    }
+
+    pub const fn from_tag(tag: u8) -> Option<FieldType> {
+        match tag {
+            1 => Some(FieldType::I32),
+            2 => Some(FieldType::I64),
+            3 => Some(FieldType::F64),
+            4 => Some(FieldType::Bool),
+            5 => Some(FieldType::Str),
+            DECIMAL_TAG..=MAX_DECIMAL_TAG => Some(FieldType::Decimal(tag - DECIMAL_TAG)),
+            _ => None,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            FieldType::I32 => "i32",
+            FieldType::I64 => "i64",
+            FieldType::F64 => "f64",
+            FieldType::Bool => "bool",
+            FieldType::Str => "str",
+            FieldType::Decimal(_) => "decimal",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field {
+    pub name: &'static str,
+    pub ty: FieldType,
+}
+
+/// Ordered description of the fields of a record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Schema {
+    pub name: &'static str,
+    pub fields: &'static [Field],
+}
+
+/// Size of an encoded record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordSize {
+    /// Every record takes exactly this many bytes.
+    Fixed(usize),
+    /// Records take at least `min` bytes, plus the length of their strings.
+    Variable { min: usize },
+}
+
+impl Schema {
+    /// Size of the slot area shared by every record of this schema.
+    pub const fn fixed_len(&self) -> usize {
+        let mut len = 0;
+        let mut i = 0;
+        while i < self.fields.len() {
+            len += self.fields[i].ty.slot_len();
+            i += 1;
+        }
+        len
+    }
+
+    pub fn record_size(&self) -> RecordSize {
+        let min = self.fixed_len();
+        if self.fields.iter().any(|field| field.ty == FieldType::Str) {
+            RecordSize::Variable { min }
+        } else {
+            RecordSize::Fixed(min)
+        }
+    }
+
+    /// FNV-1a hash of the field names and types, in order. Two schemas with
+    /// the same fingerprint encode records identically.
+    pub fn fingerprint(&self) -> u64 {
+        self.fields.iter().fold(FNV_OFFSET, |hash, field| {
+            let hash = fnv1a(hash, field.name.as_bytes());
+            fnv1a(hash, &[0, field.ty.tag()])
+        })
+    }
+
+    /// Index of the field called `name`.
+    pub fn field_index(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|field| field.name == name)
+    }
+}
+
+/// A type that can be persisted by [`crate::serialize`].
+///
+/// Usually implemented with `#[derive(Record)]`, which encodes the fields in
+/// declaration order and maps them to CSV columns in the same order.
+pub trait Record: Sized + Send + Sync + 'static {
+    /// Fields of the record, in the order they are encoded.
+    const SCHEMA: &'static Schema;
+
+    /// Appends the encoded record to `buf`.
+    fn encode(&self, buf: &mut Vec<u8>);
+
+    /// Decodes a record from exactly the bytes produced by [`Record::encode`].
+    fn decode(bytes: &[u8]) -> Result<Self>;
+
+    /// The record with every field borrowed from its encoded bytes. The
+    /// derive names it after the record, e.g. `DailyBlotterDataView`.
+    type View<'a>: Copy;
+
+    /// Reads a record without allocating, borrowing its strings from `bytes`.
+    fn view(bytes: &[u8]) -> Result<Self::View<'_>>;
+
+    /// Copies a view into an owned record.
+    fn from_view(view: Self::View<'_>) -> Self;
+
+    /// Builds a record from one CSV row, one value per field of the schema.
+    /// Errors leave the line number to the caller, see
+    /// [`crate::Error::at_line`].
+    fn from_csv_fields(fields: &[&str]) -> Result<Self>;
+
+    /// Formats every field of the record as a CSV value.
+    fn to_csv_fields(&self) -> Vec<String>;
+
+    fn record_size() -> RecordSize {
+        Self::SCHEMA.record_size()
+    }
+}
+
+/// A Rust type that can be used as the field of a derived [`Record`].
+pub trait FieldValue: Sized {
+    const TYPE: FieldType;
+
+    fn put(&self, w: &mut RowWriter);
+
+    /// The field borrowed from an encoded record: the value itself for
+    /// scalars, `&str` for strings.
+    type Ref<'a>: Copy + std::fmt::Debug + PartialEq;
+
+    fn get(r: &mut RowReader) -> Result<Self>;
+
+    fn get_ref<'a>(r: &mut RowReader<'a>) -> Result<Self::Ref<'a>>;
+
+    fn from_ref(v: Self::Ref<'_>) -> Self;
+
+    /// Parses a CSV value, describing what is wrong with it on failure.
+    fn parse(raw: &str) -> Result<Self, String>;
+
+    fn format(&self) -> String;
+}
+
+macro_rules! scalar_field_value {
+    ($ty:ty, $field_type:ident, $put:ident, $get:ident) => {
+        impl FieldValue for $ty {
+            const TYPE: FieldType = FieldType::$field_type;
+            type Ref<'a> = $ty;
+
+            fn put(&self, w: &mut RowWriter) {
+                w.$put(*self);
+            }
+
+            fn get(r: &mut RowReader) -> Result<Self> {
+                Ok(r.$get())
+            }
+
+            fn get_ref<'a>(r: &mut RowReader<'a>) -> Result<Self> {
+                Ok(r.$get())
+            }
+
+            fn from_ref(v: Self) -> Self {
+                v
+            }
+
+            fn parse(raw: &str) -> Result<Self, String> {
+                <$ty>::from_str(raw).map_err(|e| e.to_string())
+            }
+
+            fn format(&self) -> String {
+                self.to_string()
+            }
+        }
+    };
+}
+
+scalar_field_value!(i32, I32, put_i32, get_i32);
+scalar_field_value!(i64, I64, put_i64, get_i64);
+scalar_field_value!(f64, F64, put_f64, get_f64);
+scalar_field_value!(bool, Bool, put_bool, get_bool);
+
+impl FieldValue for String {
+    const TYPE: FieldType = FieldType::Str;
+    type Ref<'a> = &'a str;
+
+    fn put(&self, w: &mut RowWriter) {
+        w.put_str(self);
+    }
+
+    fn get(r: &mut RowReader) -> Result<Self> {
+        Ok(r.get_str()?.to_string())
+    }
+
+    fn get_ref<'a>(r: &mut RowReader<'a>) -> Result<&'a str> {
+        r.get_str()
+    }
+
+    fn from_ref(v: &str) -> Self {
+        v.to_string()
+    }
+
+    fn parse(raw: &str) -> Result<Self, String> {
+        Ok(raw.to_string())
+    }
+
+    fn format(&self) -> String {
+        self.clone()
+    }
+}
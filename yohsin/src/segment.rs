@@ -0,0 +1,375 @@
+//! A store of records split over size- or count-bounded segment files.
+//!
+//! Each segment is an ordinary file, as written by
+//! [`serialize_to_file`](crate::serialize::serialize_to_file). A manifest in
+//! the store directory lists the sealed segments and the global index range
+//! of their records:
+//!
+//! ```text
+//! yohsin-manifest 1
+//! next <next segment id> <next record index>
+//! segment <id> <first_record> <record_count>
+//! ```
+//!
+//! The manifest is replaced atomically (written aside, then renamed), so a
+//! segment is either fully part of the store or not at all. Removing a segment
+//! only drops it from the manifest: the global indices of the other records
+//! do not change.
+
+use crate::chunk::Layout;
+use crate::compression::Compression;
+use crate::error::{Error, Result};
+use crate::journal::Journal;
+use crate::record::Record;
+use crate::serialize::{read_chunk_table, read_header, stream_records_from, SerializeOptions};
+use crate::writer::RecordWriter;
+use futures::{StreamExt, TryStreamExt};
+use std::ops::Range;
+use std::pin::pin;
+use std::sync::Arc;
+use tokio::fs::File;
+use tokio::io::{AsyncWriteExt, BufReader};
+
+const MANIFEST: &str = "manifest";
+const MANIFEST_VERSION: u32 = 1;
+
+/// When to seal a segment and start the next one.
+#[derive(Debug, Clone)]
+pub struct SegmentOptions {
+    /// Maximum number of records per segment.
+    pub max_records: u64,
+    /// A segment is sealed once its file takes at least this many bytes.
+    /// Records not yet written out as a chunk count at an estimated size, so
+    /// a segment may end up slightly larger.
+    pub max_bytes: u64,
+    /// Number of records per chunk within a segment.
+    pub chunk_records: usize,
+    /// Layout of the chunks of new segments.
+    pub layout: Layout,
+    /// Compression of the chunks of new segments.
+    pub compression: Compression,
+}
+
+impl Default for SegmentOptions {
+    fn default() -> Self {
+        SegmentOptions {
+            max_records: 1 << 20,
+            max_bytes: 256 << 20,
+            chunk_records: SerializeOptions::default().chunk_records,
+            layout: Layout::Rows,
+            compression: Compression::None,
+        }
+    }
+}
+
+/// A sealed segment, as listed in the manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SegmentInfo {
+    pub id: u64,
+    pub first_record: u64,
+    pub record_count: u64,
+}
+
+impl SegmentInfo {
+    /// Global indices of the records of the segment.
+    pub fn records(&self) -> Range<u64> {
+        self.first_record..self.first_record + self.record_count
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Manifest {
+    next_id: u64,
+    next_record: u64,
+    segments: Vec<SegmentInfo>,
+}
+
+impl Manifest {
+    fn parse(content: &str) -> Result<Self> {
+        let malformed = |line: usize| Error::Corrupt(format!("Malformed manifest line {}", line));
+        let mut lines = content.lines().enumerate();
+        match lines.next() {
+            Some((_, line)) if line == format!("yohsin-manifest {}", MANIFEST_VERSION) => {}
+            _ => return Err(malformed(1)),
+        }
+
+        let mut manifest = Manifest::default();
+        for (idx, line) in lines {
+            let parts: Vec<&str> = line.split(' ').collect();
+            let numbers: Option<Vec<u64>> = parts[1..].iter().map(|p| p.parse().ok()).collect();
+            match (parts[0], numbers.as_deref()) {
+                ("next", Some(&[next_id, next_record])) => {
+                    manifest.next_id = next_id;
+                    manifest.next_record = next_record;
+                }
+                ("segment", Some(&[id, first_record, record_count])) => {
+                    manifest.segments.push(SegmentInfo {
+                        id,
+                        first_record,
+                        record_count,
+                    })
+                }
+                _ => return Err(malformed(idx + 1)),
+            }
+        }
+        Ok(manifest)
+    }
+
+    fn encode(&self) -> String {
+        let mut content = format!(
+            "yohsin-manifest {}\nnext {} {}\n",
+            MANIFEST_VERSION, self.next_id, self.next_record
+        );
+        for segment in &self.segments {
+            content.push_str(&format!(
+                "segment {} {} {}\n",
+                segment.id, segment.first_record, segment.record_count
+            ));
+        }
+        content
+    }
+}
+
+/// Records split over segment files in one directory.
+///
+/// Records are pushed to the active segment, which is sealed and added to the
+/// manifest once it reaches [`SegmentOptions::max_records`] records or
+/// [`SegmentOptions::max_bytes`] bytes. Only sealed segments are readable.
+pub struct SegmentedStore<T: Record> {
+    dir: String,
+    options: SegmentOptions,
+    manifest: Manifest,
+    /// Writer of the active segment, created with its first record.
+    active: Option<RecordWriter<T>>,
+    resumed_records: u64,
+}
+
+impl<T: Record> SegmentedStore<T> {
+    /// Opens the store in `dir`, creating it if needed.
+    ///
+    /// If the active segment of a previous run was interrupted, writing it
+    /// resumes: see [`SegmentedStore::resumed_records`].
+    pub async fn open(dir: &str, options: SegmentOptions) -> Result<Self> {
+        tokio::fs::create_dir_all(dir).await?;
+        let manifest = match tokio::fs::read_to_string(manifest_path(dir)).await {
+            Ok(content) => Manifest::parse(&content)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Manifest::default(),
+            Err(e) => return Err(e.into()),
+        };
+        let mut store = SegmentedStore {
+            dir: dir.to_string(),
+            options,
+            manifest,
+            active: None,
+            resumed_records: 0,
+        };
+
+        let id = store.manifest.next_id;
+        if Journal::load_stream(&store.journal_path(id))
+            .await
+            .is_some()
+        {
+            // The active segment was interrupted while being written
+            let writer = store.create_writer(id).await?;
+            store.resumed_records = writer.resumed_records();
+            store.active = Some(writer);
+        } else if let Ok(record_count) = record_count::<T>(&store.segment_path(id)).await {
+            // The active segment was sealed, but the manifest not updated
+            let _ = tokio::fs::remove_file(store.journal_path(id)).await;
+            store.add_sealed(id, record_count).await?;
+        }
+        Ok(store)
+    }
+
+    /// Number of records of the active segment already durable when an
+    /// interrupted run was resumed. They are part of the store; the caller
+    /// must not push them again.
+    pub fn resumed_records(&self) -> u64 {
+        self.resumed_records
+    }
+
+    /// Sealed segments, oldest first.
+    pub fn segments(&self) -> &[SegmentInfo] {
+        &self.manifest.segments
+    }
+
+    /// Global index of the next record pushed.
+    pub fn next_record(&self) -> u64 {
+        self.manifest.next_record + self.active.as_ref().map_or(0, |w| w.record_count())
+    }
+
+    pub fn segment_path(&self, id: u64) -> String {
+        format!("{}/segment-{:08}.bin", self.dir, id)
+    }
+
+    fn journal_path(&self, id: u64) -> String {
+        format!("{}/segment-{:08}.journal", self.dir, id)
+    }
+
+    pub async fn push(&mut self, record: T) -> Result<()> {
+        let writer = match &mut self.active {
+            Some(writer) => writer,
+            None => {
+                let writer = self.create_writer(self.manifest.next_id).await?;
+                self.active.insert(writer)
+            }
+        };
+        writer.push(record).await?;
+        if writer.record_count() >= self.options.max_records
+            || writer.estimated_len() >= self.options.max_bytes
+        {
+            self.roll().await?;
+        }
+        Ok(())
+    }
+
+    pub async fn extend<I: IntoIterator<Item = T>>(&mut self, records: I) -> Result<()> {
+        for record in records {
+            self.push(record).await?;
+        }
+        Ok(())
+    }
+
+    /// Seals the active segment, if it holds any record, making its records
+    /// readable.
+    pub async fn roll(&mut self) -> Result<()> {
+        let Some(writer) = self.active.take() else {
+            return Ok(());
+        };
+        let id = self.manifest.next_id;
+        let record_count = writer.record_count();
+        writer.finish().await?;
+        tokio::fs::remove_file(self.journal_path(id)).await?;
+        self.add_sealed(id, record_count).await
+    }
+
+    /// Seals the active segment and closes the store.
+    pub async fn close(mut self) -> Result<()> {
+        self.roll().await
+    }
+
+    /// Reads the records with global indices in `range`.
+    pub async fn read_range(&self, range: Range<u64>) -> Result<Vec<T>> {
+        if range.start > range.end || range.end > self.manifest.next_record {
+            return Err(Error::RangeOutOfBounds {
+                start: range.start as usize,
+                end: range.end as usize,
+                len: self.manifest.next_record as usize,
+            });
+        }
+
+        let mut records = Vec::with_capacity((range.end - range.start) as usize);
+        let mut next = range.start;
+        for segment in &self.manifest.segments {
+            let wanted = next.max(segment.first_record)..range.end.min(segment.records().end);
+            if wanted.start >= wanted.end {
+                continue;
+            }
+            if wanted.start != next {
+                break;
+            }
+            let path = Arc::new(self.segment_path(segment.id));
+            let start = (wanted.start - segment.first_record) as usize;
+            let count = (wanted.end - wanted.start) as usize;
+            let mut stream = pin!(stream_records_from::<T>(path, start).take(count));
+            while let Some(record) = stream.try_next().await? {
+                records.push(record);
+            }
+            next = wanted.end;
+        }
+
+        if next != range.end {
+            let end = self
+                .manifest
+                .segments
+                .iter()
+                .map(|segment| segment.first_record)
+                .find(|&first| first > next)
+                .unwrap_or(range.end)
+                .min(range.end);
+            return Err(Error::MissingRecords { start: next, end });
+        }
+        Ok(records)
+    }
+
+    /// Drops segment `id` from the store and moves its file to `archive_dir`.
+    /// Returns the path of the archived file.
+    pub async fn archive_segment(&mut self, id: u64, archive_dir: &str) -> Result<String> {
+        self.remove_from_manifest(id).await?;
+        tokio::fs::create_dir_all(archive_dir).await?;
+        let archived = format!("{}/segment-{:08}.bin", archive_dir, id);
+        tokio::fs::rename(self.segment_path(id), &archived).await?;
+        Ok(archived)
+    }
+
+    /// Drops segment `id` from the store and deletes its file.
+    pub async fn delete_segment(&mut self, id: u64) -> Result<()> {
+        self.remove_from_manifest(id).await?;
+        tokio::fs::remove_file(self.segment_path(id)).await?;
+        Ok(())
+    }
+
+    async fn create_writer(&self, id: u64) -> Result<RecordWriter<T>> {
+        let options = SerializeOptions {
+            chunk_records: self.options.chunk_records,
+            layout: self.options.layout,
+            compression: self.options.compression,
+            ..SerializeOptions::default()
+        };
+        RecordWriter::create_with_options(
+            Arc::new(self.segment_path(id)),
+            Arc::new(self.journal_path(id)),
+            &options,
+        )
+        .await
+    }
+
+    async fn add_sealed(&mut self, id: u64, record_count: u64) -> Result<()> {
+        let mut manifest = self.manifest.clone();
+        manifest.segments.push(SegmentInfo {
+            id,
+            first_record: manifest.next_record,
+            record_count,
+        });
+        manifest.next_id = id + 1;
+        manifest.next_record += record_count;
+        self.write_manifest(manifest).await
+    }
+
+    async fn remove_from_manifest(&mut self, id: u64) -> Result<()> {
+        let mut manifest = self.manifest.clone();
+        let Some(idx) = manifest.segments.iter().position(|s| s.id == id) else {
+            return Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("No sealed segment {}", id),
+            )));
+        };
+        manifest.segments.remove(idx);
+        self.write_manifest(manifest).await
+    }
+
+    /// Replaces the manifest atomically, then adopts `manifest`.
+    async fn write_manifest(&mut self, manifest: Manifest) -> Result<()> {
+        let path = manifest_path(&self.dir);
+        let tmp = format!("{}.tmp", path);
+        let mut file = tokio::fs::File::create(&tmp).await?;
+        file.write_all(manifest.encode().as_bytes()).await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&tmp, &path).await?;
+        tokio::fs::File::open(&self.dir).await?.sync_all().await?;
+        self.manifest = manifest;
+        Ok(())
+    }
+}
+
+/// Number of records of the complete file at `file_path`.
+async fn record_count<T: Record>(file_path: &str) -> Result<u64> {
+    let mut file = BufReader::new(File::open(file_path).await?);
+    let (header, header_bytes) = read_header::<T>(&mut file).await?;
+    read_chunk_table(&mut file, &header, &header_bytes).await?;
+    Ok(header.record_count)
+}
+
+fn manifest_path(dir: &str) -> String {
+    format!("{}/{}", dir, MANIFEST)
+}
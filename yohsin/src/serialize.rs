@@ -1,194 +1,703 @@
-#![allow(unused_variables)]
-
+use crate::chunk::{
+    chunk_as_rows, chunk_into_rows, decode_chunk, encode_chunk_as, ChunkEntry, ChunkTable, Layout,
+    DEFAULT_CHUNK_RECORDS,
+};
+use crate::compression::Compression;
+use crate::error::{Error, Result};
+#[cfg(feature = "failpoints")]
+use crate::failpoint;
+use crate::header::{FileHeader, HEADER_PREFIX_LEN};
+use crate::index::{index_path, source_checksum, IndexBuilder};
+use crate::journal::{layout_fingerprint, Journal};
+use crate::record::Record;
+use crate::time_index::{time_index_path, TimeIndexBuilder};
+use futures::{Stream, TryStreamExt};
+use rayon::prelude::*;
+use std::collections::BTreeSet;
 use std::ops::Range;
 use std::sync::Arc;
-use tokio::fs::File;
-use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader, BufWriter};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader};
 use tokio::sync::Mutex;
 
+/// Tuning knobs for [`serialize_to_file_with_options`].
+#[derive(Debug, Clone)]
+pub struct SerializeOptions {
+    /// Number of records per chunk. The file layout only depends on this and
+    /// on the data, so it is identical whatever the number of threads.
+    pub chunk_records: usize,
+    /// Number of tasks writing chunks concurrently.
+    pub threads: usize,
+    /// Layout of the chunks of a new file. Appending to a file keeps its
+    /// layout.
+    pub layout: Layout,
+    /// Compression of the chunks of a new file. Appending to a file keeps its
+    /// compression.
+    pub compression: Compression,
+    /// Fields to build a secondary index on once the file is written, see
+    /// [`crate::index`]. Only [`serialize_to_file_with_options`] builds them.
+    pub index_fields: Vec<&'static str>,
+    /// Time fields to build a sparse index on, see [`crate::time_index`].
+    /// Only [`serialize_to_file_with_options`] builds them.
+    pub time_index_fields: Vec<&'static str>,
+}
+
+impl Default for SerializeOptions {
+    fn default() -> Self {
+        SerializeOptions {
+            chunk_records: DEFAULT_CHUNK_RECORDS,
+            threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            layout: Layout::Rows,
+            compression: Compression::None,
+            index_fields: Vec::new(),
+            time_index_fields: Vec::new(),
+        }
+    }
+}
+
+/// Serializes `data` to `file_path` with the default [`SerializeOptions`].
 pub async fn serialize_to_file<T>(
     data: Arc<[T]>,
     file_path: Arc<String>,
-    memo_file: Arc<String>,
-) -> Result<(), Box<dyn std::error::Error>>
+    journal_file: Arc<String>,
+) -> Result<()>
 where
-    T: Send + Sync + 'static,
+    T: Record,
 {
-    let memo_content = tokio::fs::read_to_string(&*memo_file)
+    serialize_to_file_with_options(data, file_path, journal_file, &SerializeOptions::default())
         .await
-        .unwrap_or_default();
-
-    // Infer the last written index from the memo file
-    let last_written_idx: usize = memo_content.trim().parse().unwrap_or(0); // Default to 0 if memo file is empty or invalid
-
-    // Calculate the number of objects to process
-    let n_objects = data.len() - last_written_idx;
-    let object_size = std::mem::size_of::<T>();
-    let data_size = n_objects * object_size;
-
-    // Calculate the offset for the remaining data
-    let offset = last_written_idx * object_size;
-
-    // Use Arc to share data without cloning (only for the remaining data)
-    let data_bytes: Arc<[u8]> = Arc::from(unsafe {
-        std::slice::from_raw_parts(data.as_ptr().add(last_written_idx) as *const u8, data_size)
-    });
-
-    let file = File::create(&*file_path).await?;
-    let writer = BufWriter::new(file);
-    let shared_writer = Arc::new(Mutex::new(writer));
+}
 
-    // Write the number of records to the file
-    shared_writer
-        .lock()
-        .await
-        .write_all(&(n_objects as u64).to_le_bytes())
-        .await?;
+/// Serializes `data` to `file_path`.
+///
+/// The file holds a [`FileHeader`], the encoded chunks and the chunk table.
+/// Every chunk is encoded up front so that the whole layout is known; the file
+/// is then sized once and each task writes its share of the chunks at their
+/// final offsets through its own handle. The header is written last, so a
+/// file is only readable once complete.
+///
+/// Progress is recorded in a [`Journal`] at `journal_file`. If it shows an
+/// interrupted serialization of the same layout, the existing file is reused:
+/// chunks recorded as complete are checked against their expected bytes and
+/// only the missing or damaged ones are written, producing the same file as an
+/// uninterrupted run.
+pub async fn serialize_to_file_with_options<T>(
+    data: Arc<[T]>,
+    file_path: Arc<String>,
+    journal_file: Arc<String>,
+    options: &SerializeOptions,
+) -> Result<()>
+where
+    T: Record,
+{
+    let n_objects = data.len();
+    let chunk_records = options.chunk_records.max(1);
+
+    // Encode every chunk up front so that the offset of each chunk is known
+    // before anything is written
+    let chunks: Arc<[Vec<u8>]> = data
+        .par_chunks(chunk_records)
+        .map(|records| encode_chunk_as(records, options.layout, options.compression))
+        .collect::<Vec<_>>()
+        .into();
+
+    // The header has the same length whatever the counts it holds
+    let header_len = FileHeader::new::<T>(0, 0).encode().len() as u64;
+    let mut table = ChunkTable::default();
+    let mut offset = header_len;
+    for (idx, chunk) in chunks.iter().enumerate() {
+        table.entries.push(ChunkEntry {
+            offset,
+            len: chunk.len() as u64,
+            first_record: (idx * chunk_records) as u64,
+            record_count: chunk_records.min(n_objects - idx * chunk_records) as u32,
+            crc: crc32c::crc32c(chunk),
+        });
+        offset += chunk.len() as u64;
+    }
+    let file_len = offset + ChunkTable::encoded_len(table.entries.len()) as u64;
+    let mut header = FileHeader::new::<T>(n_objects as u64, offset);
+    header.layout = options.layout;
+    header.compression = options.compression;
+    let layout = layout_fingerprint(&header, &table);
+
+    // Resume an interrupted serialization of the same layout, if any
+    let existing_len = tokio::fs::metadata(&*file_path).await.map(|m| m.len());
+    let (journal, done) = match Journal::load(&journal_file).await {
+        Some(state) if state.layout == layout && existing_len.ok() == Some(file_len) => {
+            header.created_at = state.created_at;
+            let done = verify_chunks(&file_path, &table, &chunks, &state.completed).await?;
+
+            // Rewrite the journal rather than appending to a possibly torn line
+            let mut journal = Journal::begin(&journal_file, layout, state.created_at).await?;
+            journal
+                .complete(&done.iter().copied().collect::<Vec<_>>())
+                .await?;
+            (journal, done)
+        }
+        _ => {
+            let journal = Journal::begin(&journal_file, layout, header.created_at).await?;
+
+            // Size the file once, then fill it in
+            let file = File::create(&*file_path).await?;
+            file.set_len(file_len).await?;
+            file.sync_all().await?;
+            (journal, BTreeSet::new())
+        }
+    };
 
-    let num_threads = std::thread::available_parallelism()?.get();
-    let chunk_size = (n_objects + num_threads - 1) / num_threads;
+    let entries: Arc<[ChunkEntry]> = table.entries.clone().into();
+    let journal = Arc::new(Mutex::new(journal));
+    let num_threads = options.threads.max(1);
+    let chunks_per_thread = chunks.len().div_ceil(num_threads);
 
     let mut handles = Vec::new();
     for thread_id in 0..num_threads {
-        let data_bytes = Arc::clone(&data_bytes);
-        let shared_writer = Arc::clone(&shared_writer);
+        let chunks = Arc::clone(&chunks);
+        let entries = Arc::clone(&entries);
         let file_path = Arc::clone(&file_path);
-        let memo_file = Arc::clone(&memo_file);
-
-        // Calculate this thread's start and end indices
-        let start_idx = thread_id * chunk_size * object_size;
-        let end_idx = std::cmp::min(start_idx + chunk_size * object_size, data_size);
-
-        let file_offset = 8 + offset + start_idx;
+        let journal = Arc::clone(&journal);
+
+        // This thread's chunks, skipping the ones already written
+        let first = (thread_id * chunks_per_thread).min(chunks.len());
+        let last = ((thread_id + 1) * chunks_per_thread).min(chunks.len());
+        let pending: Vec<usize> = (first..last).filter(|idx| !done.contains(idx)).collect();
+        if pending.is_empty() {
+            continue;
+        }
 
         // Write to the file
         let handle = tokio::spawn(async move {
-            let data_slice = &data_bytes[start_idx..end_idx];
-
-            let mut file = File::create(&*file_path).await.unwrap();
-            file.seek(std::io::SeekFrom::Start(file_offset as u64))
-                .await
-                .unwrap();
-
-            file.write_all(data_slice).await.unwrap();
-
-            // Mark this chunk as completed in memo
-            let mut memo_file = File::create(&*memo_file).await.unwrap();
-            memo_file
-                .write_all(
-                    (last_written_idx + (end_idx / object_size))
-                        .to_string()
-                        .as_bytes(),
-                )
-                .await
-                .unwrap();
+            // Open without truncating: the file was sized above
+            let mut file = OpenOptions::new().write(true).open(&*file_path).await?;
+            for idx in pending {
+                file.seek(std::io::SeekFrom::Start(entries[idx].offset))
+                    .await?;
+
+                // A crash in the middle of a write leaves a torn chunk
+                #[cfg(feature = "failpoints")]
+                if let Err(crash) = failpoint::check(&file_path) {
+                    file.write_all(&chunks[idx][..chunks[idx].len() / 2])
+                        .await?;
+                    file.flush().await?;
+                    return Err(crash.into());
+                }
+
+                file.write_all(&chunks[idx]).await?;
+
+                // Only journal the chunk once it is durable
+                file.sync_data().await?;
+                #[cfg(feature = "failpoints")]
+                failpoint::check(&file_path)?;
+                journal.lock().await.complete(&[idx]).await?;
+            }
+
+            Ok::<(), Error>(())
         });
 
         handles.push(handle);
     }
 
+    // Wait for every task before reporting the first failure, so that no
+    // task is still writing once this function returns
+    let mut result = Ok(());
     for handle in handles {
-        handle.await?;
+        let outcome = handle
+            .await
+            .map_err(|e| Error::Io(std::io::Error::other(e)))
+            .and_then(|r| r);
+        result = result.and(outcome);
+    }
+    result?;
+
+    // Finish with the chunk table and the header, which makes the file valid
+    let header_bytes = header.encode();
+    let mut file = OpenOptions::new().write(true).open(&*file_path).await?;
+    file.seek(std::io::SeekFrom::Start(offset)).await?;
+    file.write_all(&table.encode(&header_bytes)).await?;
+    #[cfg(feature = "failpoints")]
+    failpoint::check(&file_path)?;
+    file.seek(std::io::SeekFrom::Start(0)).await?;
+    file.write_all(&header_bytes).await?;
+    file.sync_all().await?;
+
+    #[cfg(feature = "failpoints")]
+    failpoint::check(&file_path)?;
+
+    // The indexes are rebuilt from the chunks, so a resumed run writes them
+    // again before the journal is emptied
+    let source = source_checksum(&table, &header_bytes);
+    let has_indexes = !options.index_fields.is_empty() || !options.time_index_fields.is_empty();
+    let row_chunks = match has_indexes {
+        true => chunks
+            .iter()
+            .enumerate()
+            .map(|(idx, chunk)| chunk_as_rows(&header, idx, chunk))
+            .collect::<Result<Vec<_>>>()?,
+        false => Vec::new(),
+    };
+    for field in &options.index_fields {
+        let mut builder = IndexBuilder::new::<T>(field)?;
+        for (entry, chunk) in table.entries.iter().zip(&row_chunks) {
+            builder.add_chunk(entry, chunk)?;
+        }
+        builder
+            .write(&index_path(&file_path, field), source)
+            .await?;
+    }
+    for field in &options.time_index_fields {
+        let mut builder = TimeIndexBuilder::new::<T>(field)?;
+        for chunk in &row_chunks {
+            builder.add_chunk(chunk)?;
+        }
+        builder
+            .write(&time_index_path(&file_path, field), source)
+            .await?;
     }
 
-    // Empty the memo file as a sign of success
-    tokio::fs::write(&*memo_file, "").await?;
-
-    let mut writer = shared_writer.lock().await;
-    writer.flush().await?;
+    // Empty the journal as a sign of success
+    journal.lock().await.clear().await?;
 
     Ok(())
 }
 
-pub async fn deserialize_from_file<T>(
+/// Appends `data` to the file at `file_path` with the default
+/// [`SerializeOptions`].
+pub async fn append_to_file<T>(data: Arc<[T]>, file_path: Arc<String>) -> Result<()>
+where
+    T: Record,
+{
+    append_to_file_with_options(data, file_path, &SerializeOptions::default()).await
+}
+
+/// Appends `data` after the records of the file at `file_path`, which must
+/// hold records of type `T`.
+///
+/// Existing chunks are left untouched. The new chunks are written after the
+/// current chunk table, followed by a table covering every chunk; only then is
+/// the header rewritten in place to point to the new table. Until that single
+/// write the header still describes the previous content, so the file can be
+/// read at every point and a crash leaves it as it was before the append.
+pub async fn append_to_file_with_options<T>(
+    data: Arc<[T]>,
     file_path: Arc<String>,
-) -> Result<Arc<[T]>, Box<dyn std::error::Error>>
+    options: &SerializeOptions,
+) -> Result<()>
 where
-    T: Clone + 'static,
+    T: Record,
+{
+    let mut reader = BufReader::new(File::open(&*file_path).await?);
+    let (mut header, header_bytes) = read_header::<T>(&mut reader).await?;
+    let mut table = read_chunk_table(&mut reader, &header, &header_bytes).await?;
+    drop(reader);
+    if data.is_empty() {
+        return Ok(());
+    }
+
+    let chunk_records = options.chunk_records.max(1);
+    let chunks: Vec<Vec<u8>> = data
+        .par_chunks(chunk_records)
+        .map(|records| encode_chunk_as(records, header.layout, header.compression))
+        .collect();
+
+    // Anything past the current table is left over from an interrupted append
+    let mut offset =
+        header.chunk_table_offset + ChunkTable::encoded_len(table.entries.len()) as u64;
+    let first_new = table.entries.len();
+    for (chunk, records) in chunks.iter().zip(data.chunks(chunk_records)) {
+        table.entries.push(ChunkEntry {
+            offset,
+            len: chunk.len() as u64,
+            first_record: table.record_count(),
+            record_count: records.len() as u32,
+            crc: crc32c::crc32c(chunk),
+        });
+        offset += chunk.len() as u64;
+    }
+
+    let mut file = OpenOptions::new().write(true).open(&*file_path).await?;
+    for (entry, chunk) in table.entries[first_new..].iter().zip(&chunks) {
+        file.seek(std::io::SeekFrom::Start(entry.offset)).await?;
+
+        // A crash in the middle of a write leaves a torn chunk
+        #[cfg(feature = "failpoints")]
+        if let Err(crash) = failpoint::check(&file_path) {
+            file.write_all(&chunk[..chunk.len() / 2]).await?;
+            file.flush().await?;
+            return Err(crash.into());
+        }
+
+        file.write_all(chunk).await?;
+    }
+
+    // The header keeps its length: only its counts change
+    header.record_count = table.record_count();
+    header.chunk_table_offset = offset;
+    let header = header.encode();
+    let table = table.encode(&header);
+    file.seek(std::io::SeekFrom::Start(offset)).await?;
+    file.write_all(&table).await?;
+    file.set_len(offset + table.len() as u64).await?;
+    file.sync_all().await?;
+
+    #[cfg(feature = "failpoints")]
+    failpoint::check(&file_path)?;
+
+    file.seek(std::io::SeekFrom::Start(0)).await?;
+    file.write_all(&header).await?;
+    file.sync_all().await?;
+    Ok(())
+}
+
+/// Returns the chunks of `completed` whose bytes in the file are the expected
+/// ones.
+async fn verify_chunks(
+    file_path: &str,
+    table: &ChunkTable,
+    chunks: &[Vec<u8>],
+    completed: &BTreeSet<usize>,
+) -> Result<BTreeSet<usize>> {
+    let mut file = BufReader::new(File::open(file_path).await?);
+    let mut verified = BTreeSet::new();
+    for &idx in completed.iter().filter(|&&idx| idx < chunks.len()) {
+        if read_chunk(&mut file, &table.entries[idx]).await? == chunks[idx] {
+            verified.insert(idx);
+        }
+    }
+    Ok(verified)
+}
+
+/// Reads the header at the start of `file` and checks that it describes
+/// records of type `T`. Returns the header and its raw bytes.
+pub(crate) async fn read_header<T: Record>(
+    file: &mut BufReader<File>,
+) -> Result<(FileHeader, Vec<u8>)> {
+    let mut bytes = vec![0u8; HEADER_PREFIX_LEN];
+    read_header_bytes(file, &mut bytes).await?;
+    let header_len = FileHeader::encoded_len(&bytes)?;
+    if header_len as u64 > file.get_ref().metadata().await?.len() {
+        return Err(Error::Truncated { what: "header" });
+    }
+    bytes.resize(header_len, 0);
+    read_header_bytes(file, &mut bytes[HEADER_PREFIX_LEN..]).await?;
+
+    let header = FileHeader::decode(&bytes)?;
+    header.validate::<T>()?;
+    Ok((header, bytes))
+}
+
+/// Fills `buf`, reporting a short file as a truncated header.
+async fn read_header_bytes(file: &mut BufReader<File>, buf: &mut [u8]) -> Result<()> {
+    file.read_exact(buf)
+        .await
+        .map_err(Error::reading("header"))?;
+    Ok(())
+}
+
+/// Reads the chunk table that `header` points to, checking it against its
+/// checksum together with the raw header bytes.
+pub(crate) async fn read_chunk_table(
+    file: &mut BufReader<File>,
+    header: &FileHeader,
+    header_bytes: &[u8],
+) -> Result<ChunkTable> {
+    // The chunk table is the last thing written to the file
+    file.seek(std::io::SeekFrom::Start(header.chunk_table_offset))
+        .await?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).await?;
+
+    let table = ChunkTable::decode(&bytes, header_bytes)?;
+    table.check_layout(header, header_bytes.len())?;
+    Ok(table)
+}
+
+async fn read_chunk(file: &mut BufReader<File>, entry: &ChunkEntry) -> Result<Vec<u8>> {
+    read_at(file, entry.offset, entry.len as usize, "chunk").await
+}
+
+/// Reads the `len` bytes at `offset`, reporting a short file as a truncated
+/// `what`.
+pub(crate) async fn read_at(
+    file: &mut BufReader<File>,
+    offset: u64,
+    len: usize,
+    what: &'static str,
+) -> Result<Vec<u8>> {
+    file.seek(std::io::SeekFrom::Start(offset)).await?;
+    let mut bytes = vec![0u8; len];
+    file.read_exact(&mut bytes)
+        .await
+        .map_err(Error::reading(what))?;
+    Ok(bytes)
+}
+
+/// Reads chunk number `idx` of `table`, checking it against its checksum,
+/// and returns it in the row layout whatever the layout of the file.
+pub(crate) async fn read_verified_chunk(
+    file: &mut BufReader<File>,
+    header: &FileHeader,
+    table: &ChunkTable,
+    idx: usize,
+) -> Result<Vec<u8>> {
+    let entry = &table.entries[idx];
+    let bytes = read_chunk(file, entry).await?;
+    entry.verify(idx, &bytes)?;
+    chunk_into_rows(header, idx, bytes)
+}
+
+/// Deserializes every record of a file, checking every chunk and the chunk
+/// table against their checksums.
+pub async fn deserialize_from_file<T>(file_path: Arc<String>) -> Result<Arc<[T]>>
+where
+    T: Record,
 {
     let file = File::open(&*file_path).await?;
     let mut file = BufReader::new(file);
 
-    let mut num_records_bytes = [0u8; 8];
-    file.read_exact(&mut num_records_bytes).await?;
-    let num_records = u64::from_le_bytes(num_records_bytes) as usize;
-
-    let mut binary_data = Vec::new();
-    file.read_to_end(&mut binary_data).await?;
-
-    // Reconstruct the Arc<[T]>
-    let object_size = std::mem::size_of::<T>();
-    let mut retrieved_data = Vec::with_capacity(num_records);
-    for i in 0..num_records {
-        let offset = i * object_size;
-        if offset + object_size > binary_data.len() {
-            panic!(
-                "Invalid offset: {} (binary data length: {})",
-                offset,
-                binary_data.len()
-            );
-        }
+    let (header, header_bytes) = read_header::<T>(&mut file).await?;
+    let table = read_chunk_table(&mut file, &header, &header_bytes).await?;
 
-        let record_ptr = binary_data[offset..offset + object_size].as_ptr() as *const T;
-        let record = unsafe { &*record_ptr }; // Safe because we know the layout
-        retrieved_data.push((*record).clone());
+    let mut retrieved_data = Vec::with_capacity(header.capacity_hint());
+    for (idx, entry) in table.entries.iter().enumerate() {
+        let chunk = read_verified_chunk(&mut file, &header, &table, idx).await?;
+        retrieved_data.extend(decode_chunk::<T>(&chunk, 0..entry.record_count as usize)?);
     }
 
     Ok(Arc::from(retrieved_data))
 }
 
 /// Deserializes a range of elements from a file into an Arc<[T]>.
+///
+/// Only the chunks holding records of `range` are read and checked against
+/// their checksums.
 pub async fn deserialize_range_from_file<T>(
     file_path: Arc<String>,
     range: Range<usize>,
-) -> Result<Arc<[T]>, Box<dyn std::error::Error>>
+) -> Result<Arc<[T]>>
 where
-    T: Clone + 'static,
+    T: Record,
 {
     let file = File::open(&*file_path).await?;
     let mut file = BufReader::new(file);
 
-    let mut num_records_bytes = [0u8; 8];
-    file.read_exact(&mut num_records_bytes).await?;
-    let num_records = u64::from_le_bytes(num_records_bytes) as usize;
+    let (header, header_bytes) = read_header::<T>(&mut file).await?;
+    let num_records = header.record_count as usize;
 
     // Validate the range
-    if range.end > num_records {
-        return Err(format!(
-            "Invalid range: {}..{} (file contains {} records)",
-            range.start, range.end, num_records
-        )
-        .into());
+    if range.start > range.end || range.end > num_records {
+        return Err(Error::RangeOutOfBounds {
+            start: range.start,
+            end: range.end,
+            len: num_records,
+        });
     }
 
-    // Calculate the seek position and scope
-    let object_size = std::mem::size_of::<T>();
-    let start_offset = 8 + range.start * object_size; // 8 bytes for the number of records
-    let end_offset = 8 + range.end * object_size;
+    let table = read_chunk_table(&mut file, &header, &header_bytes).await?;
+    let wanted = range.start as u64..range.end as u64;
 
-    file.seek(std::io::SeekFrom::Start(start_offset as u64))
-        .await?;
+    let mut retrieved_data = Vec::with_capacity(range.len());
+    for idx in table.chunks_for(&wanted) {
+        let entry = &table.entries[idx];
+        let chunk = read_verified_chunk(&mut file, &header, &table, idx).await?;
+
+        // Positions of the wanted records relative to the chunk
+        let records = entry.records();
+        let start = wanted.start.max(records.start) - records.start;
+        let end = wanted.end.min(records.end) - records.start;
+        retrieved_data.extend(decode_chunk::<T>(&chunk, start as usize..end as usize)?);
+    }
 
-    // Read the required data into a buffer
-    let mut binary_data = vec![0u8; end_offset - start_offset];
-    file.read_exact(&mut binary_data).await?;
+    Ok(Arc::from(retrieved_data))
+}
 
-    // Reconstruct the Arc<[T]> for the range
-    let mut retrieved_data = Vec::with_capacity(range.len());
-    for i in 0..range.len() {
-        let offset = i * object_size;
-        if offset + object_size > binary_data.len() {
-            panic!(
-                "Invalid offset: {} (binary data length: {})",
-                offset,
-                binary_data.len()
-            );
+/// Streams every record of a file, see [`stream_records_from`].
+pub fn stream_records<T: Record>(file_path: Arc<String>) -> impl Stream<Item = Result<T>> {
+    stream_records_from(file_path, 0)
+}
+
+/// Streams the records of a file from record number `start` on.
+///
+/// The file is read one chunk at a time, each checked against its checksum
+/// before its records are yielded, so memory use only depends on the chunk
+/// size. The stream ends after the first error.
+pub fn stream_records_from<T: Record>(
+    file_path: Arc<String>,
+    start: usize,
+) -> impl Stream<Item = Result<T>> {
+    let chunks = futures::stream::try_unfold(None, move |state| {
+        let file_path = Arc::clone(&file_path);
+        async move {
+            let mut state = match state {
+                Some(state) => state,
+                None => ChunkCursor::open::<T>(&file_path, start).await?,
+            };
+            let Some(idx) = state.chunks.next() else {
+                return Ok::<_, Error>(None);
+            };
+            let chunk =
+                read_verified_chunk(&mut state.file, &state.header, &state.table, idx).await?;
+            let records = state.chunk_range(idx);
+            Ok(Some((decode_chunk::<T>(&chunk, records)?, Some(state))))
+        }
+    });
+    chunks
+        .map_ok(|records| futures::stream::iter(records.into_iter().map(Ok)))
+        .try_flatten()
+}
+
+/// Position of a stream of records in its file.
+struct ChunkCursor<F> {
+    file: F,
+    header: FileHeader,
+    table: ChunkTable,
+    /// Chunks left to read.
+    chunks: Range<usize>,
+    start: u64,
+}
+
+impl<F> ChunkCursor<F> {
+    /// Positions of the records of chunk `idx` to yield, relative to the
+    /// chunk.
+    fn chunk_range(&self, idx: usize) -> Range<usize> {
+        let entry = &self.table.entries[idx];
+        let skip = self.start.saturating_sub(entry.first_record) as usize;
+        skip.min(entry.record_count as usize)..entry.record_count as usize
+    }
+
+    fn new(file: F, header: FileHeader, table: ChunkTable, start: usize) -> Result<Self> {
+        if start as u64 > header.record_count {
+            return Err(Error::RangeOutOfBounds {
+                start,
+                end: header.record_count as usize,
+                len: header.record_count as usize,
+            });
         }
+        let start = start as u64;
+        let chunks = table.chunks_for(&(start..header.record_count));
+        Ok(ChunkCursor {
+            file,
+            header,
+            table,
+            chunks,
+            start,
+        })
+    }
+}
 
-        let record_ptr = binary_data[offset..offset + object_size].as_ptr() as *const T;
-        let record = unsafe { &*record_ptr }; // Safe because we know the layout
-        retrieved_data.push((*record).clone()); // Clone the record to get an owned value
+impl ChunkCursor<BufReader<File>> {
+    async fn open<T: Record>(file_path: &str, start: usize) -> Result<Self> {
+        let mut file = BufReader::new(File::open(file_path).await?);
+        let (header, header_bytes) = read_header::<T>(&mut file).await?;
+        let table = read_chunk_table(&mut file, &header, &header_bytes).await?;
+        ChunkCursor::new(file, header, table, start)
     }
+}
 
-    Ok(Arc::from(retrieved_data))
+/// Blocking twin of [`stream_records_from`]: iterates over the records of a
+/// file from record number `start` on, one chunk in memory at a time.
+pub fn iter_records_from<T: Record>(file_path: &str, start: usize) -> Result<RecordIter<T>> {
+    let mut file = std::io::BufReader::new(std::fs::File::open(file_path)?);
+    let (header, header_bytes) = blocking::read_header::<T>(&mut file)?;
+    let table = blocking::read_chunk_table(&mut file, &header, &header_bytes)?;
+    Ok(RecordIter {
+        cursor: Some(ChunkCursor::new(file, header, table, start)?),
+        records: Vec::new().into_iter(),
+    })
+}
+
+/// Blocking twin of [`stream_records`].
+pub fn iter_records<T: Record>(file_path: &str) -> Result<RecordIter<T>> {
+    iter_records_from(file_path, 0)
+}
+
+/// Iterator returned by [`iter_records_from`]. It ends after the first error.
+pub struct RecordIter<T> {
+    /// `None` once an error was returned.
+    cursor: Option<ChunkCursor<std::io::BufReader<std::fs::File>>>,
+    /// Records of the current chunk not yielded yet.
+    records: std::vec::IntoIter<T>,
+}
+
+impl<T: Record> RecordIter<T> {
+    fn next_chunk(&mut self) -> Result<Option<Vec<T>>> {
+        let Some(cursor) = self.cursor.as_mut() else {
+            return Ok(None);
+        };
+        let Some(idx) = cursor.chunks.next() else {
+            return Ok(None);
+        };
+        let entry = &cursor.table.entries[idx];
+        let chunk = blocking::read_chunk(&mut cursor.file, entry)?;
+        entry.verify(idx, &chunk)?;
+        let chunk = chunk_into_rows(&cursor.header, idx, chunk)?;
+        decode_chunk::<T>(&chunk, cursor.chunk_range(idx)).map(Some)
+    }
+}
+
+impl<T: Record> Iterator for RecordIter<T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        loop {
+            if let Some(record) = self.records.next() {
+                return Some(Ok(record));
+            }
+            match self.next_chunk() {
+                Ok(Some(records)) => self.records = records.into_iter(),
+                Ok(None) => return None,
+                Err(e) => {
+                    self.cursor = None;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+/// Synchronous versions of the read helpers, for [`RecordIter`].
+mod blocking {
+    use super::*;
+    use std::io::{Read, Seek, SeekFrom};
+
+    type Reader = std::io::BufReader<std::fs::File>;
+
+    pub(super) fn read_header<T: Record>(file: &mut Reader) -> Result<(FileHeader, Vec<u8>)> {
+        let mut bytes = vec![0u8; HEADER_PREFIX_LEN];
+        file.read_exact(&mut bytes)
+            .map_err(Error::reading("header"))?;
+        let header_len = FileHeader::encoded_len(&bytes)?;
+        if header_len as u64 > file.get_ref().metadata()?.len() {
+            return Err(Error::Truncated { what: "header" });
+        }
+        bytes.resize(header_len, 0);
+        file.read_exact(&mut bytes[HEADER_PREFIX_LEN..])
+            .map_err(Error::reading("header"))?;
+
+        let header = FileHeader::decode(&bytes)?;
+        header.validate::<T>()?;
+        Ok((header, bytes))
+    }
+
+    pub(super) fn read_chunk_table(
+        file: &mut Reader,
+        header: &FileHeader,
+        header_bytes: &[u8],
+    ) -> Result<ChunkTable> {
+        file.seek(SeekFrom::Start(header.chunk_table_offset))?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+
+        let table = ChunkTable::decode(&bytes, header_bytes)?;
+        table.check_layout(header, header_bytes.len())?;
+        Ok(table)
+    }
+
+    pub(super) fn read_chunk(file: &mut Reader, entry: &ChunkEntry) -> Result<Vec<u8>> {
+        file.seek(SeekFrom::Start(entry.offset))?;
+        let mut bytes = vec![0u8; entry.len as usize];
+        file.read_exact(&mut bytes)
+            .map_err(Error::reading("chunk"))?;
+        Ok(bytes)
+    }
 }
@@ -0,0 +1,196 @@
+//! Sparse indexes of a time field: the smallest and largest value the field
+//! takes in each chunk.
+//!
+//! The time index of field `<field>` of `<file>` lives next to it, at
+//! `<file>.<field>.tidx`:
+//!
+//! ```text
+//! magic            8 bytes  b"YOHSTIX\0"
+//! version          u16
+//! field            u16 length + name, u8 type tag
+//! source           u32      table checksum of the indexed file
+//! chunk_count      u32
+//! chunks           per chunk: min i64, max i64
+//! crc              u32      CRC32C of everything above
+//! ```
+//!
+//! The preamble is the one of [`crate::index`], and a time index no longer
+//! matching its file is rejected the same way. Only `i64` and `i32` fields,
+//! such as epoch timestamps, can be indexed.
+
+use crate::chunk::{chunk_records, decode_chunk, ChunkTable};
+use crate::error::{Error, Result};
+use crate::index::{field_of, put_crc, put_preamble, read_preamble, source_checksum, write_aside};
+use crate::record::{Field, FieldType, Record};
+use crate::serialize::{read_chunk_table, read_header, read_verified_chunk};
+use std::sync::Arc;
+use tokio::fs::File;
+use tokio::io::BufReader;
+
+pub const TIME_INDEX_MAGIC: [u8; 8] = *b"YOHSTIX\0";
+
+/// Path of the time index of `field` for the file at `file_path`.
+pub fn time_index_path(file_path: &str, field: &str) -> String {
+    format!("{}.{}.tidx", file_path, field)
+}
+
+/// Looks up the time field `name` of `T`, returning the field and the offset
+/// of its slot in an encoded record.
+fn time_field<T: Record>(name: &str) -> Result<(&'static Field, usize)> {
+    let (field, slot) = field_of::<T>(name)?;
+    match field.ty {
+        FieldType::I64 | FieldType::I32 => Ok((field, slot)),
+        ty => Err(Error::UnsupportedFieldType {
+            field: field.name,
+            ty,
+        }),
+    }
+}
+
+/// Reads the time field whose slot starts at `slot` from an encoded record.
+fn record_time(record: &[u8], slot: usize, ty: FieldType) -> Result<i64> {
+    let bytes = record
+        .get(slot..slot + ty.slot_len())
+        .ok_or_else(|| Error::Corrupt("Indexed field lies outside of the record".into()))?;
+    Ok(match ty {
+        FieldType::I32 => i32::from_le_bytes(bytes.try_into().unwrap()) as i64,
+        _ => i64::from_le_bytes(bytes.try_into().unwrap()),
+    })
+}
+
+/// Collects the bounds of a time field, chunk by chunk.
+pub(crate) struct TimeIndexBuilder {
+    field: &'static Field,
+    slot: usize,
+    bounds: Vec<(i64, i64)>,
+}
+
+impl TimeIndexBuilder {
+    pub(crate) fn new<T: Record>(field: &str) -> Result<Self> {
+        let (field, slot) = time_field::<T>(field)?;
+        Ok(TimeIndexBuilder {
+            field,
+            slot,
+            bounds: Vec::new(),
+        })
+    }
+
+    /// Adds the bounds of the next chunk. A chunk without records gets
+    /// `min > max`, so that no window overlaps it.
+    pub(crate) fn add_chunk(&mut self, chunk: &[u8]) -> Result<()> {
+        let mut bounds = (i64::MAX, i64::MIN);
+        for bytes in chunk_records(chunk)? {
+            let time = record_time(bytes, self.slot, self.field.ty)?;
+            bounds = (bounds.0.min(time), bounds.1.max(time));
+        }
+        self.bounds.push(bounds);
+        Ok(())
+    }
+
+    /// Writes the time index of the file identified by `source` to `path`.
+    pub(crate) async fn write(self, path: &str, source: u32) -> Result<()> {
+        let mut buf = Vec::with_capacity(64 + 16 * self.bounds.len());
+        put_preamble(&mut buf, TIME_INDEX_MAGIC, self.field, source);
+        buf.extend_from_slice(&(self.bounds.len() as u32).to_le_bytes());
+        for (min, max) in &self.bounds {
+            buf.extend_from_slice(&min.to_le_bytes());
+            buf.extend_from_slice(&max.to_le_bytes());
+        }
+        put_crc(&mut buf);
+        write_aside(path, &buf).await
+    }
+}
+
+/// Builds (or rebuilds) the time index of `field` for the file at
+/// `file_path`. Every chunk is read and checked against its checksum.
+pub async fn build_time_index<T: Record>(file_path: &str, field: &str) -> Result<()> {
+    let mut builder = TimeIndexBuilder::new::<T>(field)?;
+    let mut file = BufReader::new(File::open(file_path).await?);
+    let (header, header_bytes) = read_header::<T>(&mut file).await?;
+    let table = read_chunk_table(&mut file, &header, &header_bytes).await?;
+    for idx in 0..table.entries.len() {
+        builder.add_chunk(&read_verified_chunk(&mut file, &header, &table, idx).await?)?;
+    }
+    builder
+        .write(
+            &time_index_path(file_path, field),
+            source_checksum(&table, &header_bytes),
+        )
+        .await
+}
+
+/// Loads the bounds of every chunk of `table` from the time index at `path`.
+async fn load_bounds(
+    path: &str,
+    field: &Field,
+    table: &ChunkTable,
+    source: u32,
+) -> Result<Vec<(i64, i64)>> {
+    let bytes = tokio::fs::read(path).await?;
+    let mut r = read_preamble(&bytes, path, TIME_INDEX_MAGIC, field, source)?;
+    let count = r.u32().ok_or(Error::Truncated { what: "index" })? as usize;
+    if count != table.entries.len() {
+        return Err(Error::Corrupt(format!(
+            "Time index holds {} chunks, file holds {}",
+            count,
+            table.entries.len()
+        )));
+    }
+    let mut bounds = Vec::with_capacity(count);
+    for _ in 0..count {
+        let (Some(min), Some(max)) = (r.u64(), r.u64()) else {
+            return Err(Error::Truncated { what: "index" });
+        };
+        bounds.push((min as i64, max as i64));
+    }
+    Ok(bounds)
+}
+
+/// Deserializes the records whose time `field` lies in `from..to`, in file
+/// order.
+///
+/// The time index of `field` tells which chunks may hold such records: only
+/// these are read and checked against their checksums, and only the records
+/// in the window are decoded.
+pub async fn deserialize_time_range<T>(
+    file_path: Arc<String>,
+    field: &str,
+    from: i64,
+    to: i64,
+) -> Result<Arc<[T]>>
+where
+    T: Record,
+{
+    let (field, slot) = time_field::<T>(field)?;
+    let mut file = BufReader::new(File::open(&*file_path).await?);
+    let (header, header_bytes) = read_header::<T>(&mut file).await?;
+    let table = read_chunk_table(&mut file, &header, &header_bytes).await?;
+    let bounds = load_bounds(
+        &time_index_path(&file_path, field.name),
+        field,
+        &table,
+        source_checksum(&table, &header_bytes),
+    )
+    .await?;
+
+    let mut retrieved_data = Vec::new();
+    for (idx, &(min, max)) in bounds.iter().enumerate() {
+        if max < from || min >= to {
+            continue;
+        }
+        let chunk = read_verified_chunk(&mut file, &header, &table, idx).await?;
+        if from <= min && max < to {
+            let count = table.entries[idx].record_count as usize;
+            retrieved_data.extend(decode_chunk::<T>(&chunk, 0..count)?);
+            continue;
+        }
+        for bytes in chunk_records(&chunk)? {
+            let time = record_time(bytes, slot, field.ty)?;
+            if from <= time && time < to {
+                retrieved_data.push(T::decode(bytes)?);
+            }
+        }
+    }
+
+    Ok(Arc::from(retrieved_data))
+}
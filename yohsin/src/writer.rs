@@ -0,0 +1,302 @@
+//! Incremental serialization, for data that does not fit in memory at once.
+
+use crate::chunk::{encode_chunk_as, ChunkEntry, ChunkTable, Layout};
+use crate::compression::Compression;
+use crate::error::Result;
+#[cfg(feature = "failpoints")]
+use crate::failpoint;
+use crate::header::FileHeader;
+use crate::journal::Journal;
+use crate::record::Record;
+use crate::serialize::SerializeOptions;
+use std::io::SeekFrom;
+use std::sync::Arc;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::mpsc::Receiver;
+
+/// Writes records to a file as they come, one chunk at a time.
+///
+/// The file has the same format as one written by
+/// [`serialize_to_file`](crate::serialize::serialize_to_file): chunks follow
+/// each other after room left for the header, and [`RecordWriter::finish`]
+/// writes the chunk table and then the header, which makes the file valid.
+///
+/// Every chunk is synced and recorded in a [`Journal`] before the next one is
+/// written. A writer created over the journal of an interrupted run keeps the
+/// chunks that are intact and continues after them: the caller skips the first
+/// [`RecordWriter::resumed_records`] records of its input and pushes the rest,
+/// producing the same file as an uninterrupted run.
+pub struct RecordWriter<T> {
+    file: File,
+    /// Key of the failpoints of this file.
+    #[cfg_attr(not(feature = "failpoints"), allow(dead_code))]
+    file_path: Arc<String>,
+    journal: Journal,
+    chunk_records: usize,
+    layout: Layout,
+    compression: Compression,
+    created_at: u64,
+    /// Records of the chunk being filled.
+    pending: Vec<T>,
+    /// Chunks written so far.
+    table: ChunkTable,
+    /// End of the last chunk written.
+    offset: u64,
+    resumed_records: u64,
+}
+
+impl<T: Record> RecordWriter<T> {
+    /// Starts writing `file_path` with the default [`SerializeOptions`].
+    pub async fn create(file_path: Arc<String>, journal_file: Arc<String>) -> Result<Self> {
+        Self::create_with_options(file_path, journal_file, &SerializeOptions::default()).await
+    }
+
+    /// Starts writing `file_path`, or resumes writing it if `journal_file`
+    /// records an interrupted run with the same record type, chunk size,
+    /// layout and compression.
+    ///
+    /// Only [`SerializeOptions::chunk_records`], [`SerializeOptions::layout`]
+    /// and [`SerializeOptions::compression`] apply: chunks are written in
+    /// order, by the task pushing the records.
+    pub async fn create_with_options(
+        file_path: Arc<String>,
+        journal_file: Arc<String>,
+        options: &SerializeOptions,
+    ) -> Result<Self> {
+        let chunk_records = options.chunk_records.max(1);
+        let layout = options.layout;
+        let compression = options.compression;
+        let schema_hash = T::SCHEMA.fingerprint();
+
+        // The header has the same length whatever the counts it holds
+        let header_len = FileHeader::new::<T>(0, 0).encode().len() as u64;
+
+        let resumable = Journal::load_stream(&journal_file).await.filter(|state| {
+            state.schema_hash == schema_hash
+                && state.chunk_records == chunk_records
+                && state.layout == layout
+                && state.compression == compression
+        });
+        if let Some(state) = resumable {
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&*file_path)
+                .await;
+            if let Ok(mut file) = file {
+                let table = ChunkTable {
+                    entries: intact_prefix(&mut file, &state.entries, header_len).await?,
+                };
+                let offset = table
+                    .entries
+                    .last()
+                    .map_or(header_len, |e| e.offset + e.len);
+
+                // Drop whatever follows the intact chunks, and any header a
+                // crash before clearing the journal left behind
+                file.set_len(offset).await?;
+                file.seek(SeekFrom::Start(0)).await?;
+                file.write_all(&vec![0u8; header_len as usize]).await?;
+                file.sync_all().await?;
+
+                // Rewrite the journal rather than appending to a possibly torn line
+                let mut journal = Journal::begin_stream(
+                    &journal_file,
+                    schema_hash,
+                    chunk_records,
+                    state.created_at,
+                    layout,
+                    compression,
+                )
+                .await?;
+                journal.append(&table.entries).await?;
+
+                return Ok(RecordWriter {
+                    file,
+                    file_path,
+                    journal,
+                    chunk_records,
+                    layout,
+                    compression,
+                    created_at: state.created_at,
+                    pending: Vec::with_capacity(chunk_records),
+                    resumed_records: table.record_count(),
+                    table,
+                    offset,
+                });
+            }
+        }
+
+        let created_at = FileHeader::new::<T>(0, 0).created_at;
+        let journal = Journal::begin_stream(
+            &journal_file,
+            schema_hash,
+            chunk_records,
+            created_at,
+            layout,
+            compression,
+        )
+        .await?;
+
+        // Leave room for the header, written last
+        let mut file = File::create(&*file_path).await?;
+        file.write_all(&vec![0u8; header_len as usize]).await?;
+
+        Ok(RecordWriter {
+            file,
+            file_path,
+            journal,
+            chunk_records,
+            layout,
+            compression,
+            created_at,
+            pending: Vec::with_capacity(chunk_records),
+            table: ChunkTable::default(),
+            offset: header_len,
+            resumed_records: 0,
+        })
+    }
+
+    /// Number of records already durable when an interrupted run was resumed.
+    /// They are part of the file; the caller must not push them again.
+    pub fn resumed_records(&self) -> u64 {
+        self.resumed_records
+    }
+
+    /// Length of the file so far: the header and the chunks written, without
+    /// the pending records nor the chunk table written by `finish`.
+    pub fn bytes_written(&self) -> u64 {
+        self.offset
+    }
+
+    /// [`RecordWriter::bytes_written`] plus an estimate of the pending
+    /// records: the average size of the records already written, or their
+    /// minimum size before the first chunk.
+    pub fn estimated_len(&self) -> u64 {
+        let pending = self.pending.len() as u64;
+        let written = self.table.record_count();
+        let per_record = match self.table.entries.first() {
+            Some(first) if written > 0 => (self.offset - first.offset).div_ceil(written),
+            _ => T::SCHEMA.fixed_len() as u64,
+        };
+        self.offset + pending * per_record
+    }
+
+    /// Number of records in the file so far, including pending ones.
+    pub fn record_count(&self) -> u64 {
+        self.table.record_count() + self.pending.len() as u64
+    }
+
+    pub async fn push(&mut self, record: T) -> Result<()> {
+        self.pending.push(record);
+        if self.pending.len() == self.chunk_records {
+            self.write_chunk().await?;
+        }
+        Ok(())
+    }
+
+    pub async fn extend<I: IntoIterator<Item = T>>(&mut self, records: I) -> Result<()> {
+        for record in records {
+            self.push(record).await?;
+        }
+        Ok(())
+    }
+
+    /// Pushes every record received on `records` until the channel closes.
+    pub async fn consume(&mut self, mut records: Receiver<T>) -> Result<()> {
+        while let Some(record) = records.recv().await {
+            self.push(record).await?;
+        }
+        Ok(())
+    }
+
+    /// Writes the last chunk, the chunk table and the header, then clears the
+    /// journal.
+    pub async fn finish(mut self) -> Result<()> {
+        if !self.pending.is_empty() {
+            self.write_chunk().await?;
+        }
+
+        let mut header = FileHeader::new::<T>(self.table.record_count(), self.offset);
+        header.created_at = self.created_at;
+        header.layout = self.layout;
+        header.compression = self.compression;
+        let header = header.encode();
+
+        let table = self.table.encode(&header);
+        self.file.seek(SeekFrom::Start(self.offset)).await?;
+        self.file.write_all(&table).await?;
+        #[cfg(feature = "failpoints")]
+        failpoint::check(&self.file_path)?;
+        self.file.seek(SeekFrom::Start(0)).await?;
+        self.file.write_all(&header).await?;
+        self.file.sync_all().await?;
+
+        #[cfg(feature = "failpoints")]
+        failpoint::check(&self.file_path)?;
+
+        // Empty the journal as a sign of success
+        self.journal.clear().await?;
+        Ok(())
+    }
+
+    async fn write_chunk(&mut self) -> Result<()> {
+        let chunk = encode_chunk_as(&self.pending, self.layout, self.compression);
+        let entry = ChunkEntry {
+            offset: self.offset,
+            len: chunk.len() as u64,
+            first_record: self.table.record_count(),
+            record_count: self.pending.len() as u32,
+            crc: crc32c::crc32c(&chunk),
+        };
+
+        self.file.seek(SeekFrom::Start(entry.offset)).await?;
+
+        // A crash in the middle of a write leaves a torn chunk
+        #[cfg(feature = "failpoints")]
+        if let Err(crash) = failpoint::check(&self.file_path) {
+            self.file.write_all(&chunk[..chunk.len() / 2]).await?;
+            self.file.flush().await?;
+            return Err(crash.into());
+        }
+
+        self.file.write_all(&chunk).await?;
+
+        // Only journal the chunk once it is durable
+        self.file.sync_data().await?;
+        #[cfg(feature = "failpoints")]
+        failpoint::check(&self.file_path)?;
+        self.journal.append(&[entry]).await?;
+
+        self.table.entries.push(entry);
+        self.offset += entry.len;
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+/// The longest prefix of `entries` whose chunks are intact in `file`.
+async fn intact_prefix(
+    file: &mut File,
+    entries: &[ChunkEntry],
+    header_len: u64,
+) -> Result<Vec<ChunkEntry>> {
+    let file_len = file.metadata().await?.len();
+    let mut intact = Vec::new();
+    let mut offset = header_len;
+    for (idx, entry) in entries.iter().enumerate() {
+        if entry.offset != offset || entry.offset + entry.len > file_len {
+            break;
+        }
+        file.seek(SeekFrom::Start(entry.offset)).await?;
+        let mut bytes = vec![0u8; entry.len as usize];
+        file.read_exact(&mut bytes).await?;
+        if entry.verify(idx, &bytes).is_err() {
+            break;
+        }
+        intact.push(*entry);
+        offset += entry.len;
+    }
+    Ok(intact)
+}
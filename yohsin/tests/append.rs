@@ -0,0 +1,123 @@
+use std::sync::Arc;
+use yohsin::failpoint;
+use yohsin::order_struct::DailyBlotterData;
+use yohsin::serialize::{
+    append_to_file_with_options, deserialize_from_file, deserialize_range_from_file,
+    serialize_to_file_with_options, SerializeOptions,
+};
+use yohsin::{Error, Record};
+
+#[derive(Debug, PartialEq, Clone, Record)]
+struct Position {
+    account: i64,
+    qty: i64,
+}
+
+fn options() -> SerializeOptions {
+    SerializeOptions {
+        chunk_records: 16,
+        ..SerializeOptions::default()
+    }
+}
+
+#[tokio::test]
+async fn test_append_to_file() -> Result<(), Box<dyn std::error::Error>> {
+    let original_data = DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?;
+
+    let file_path = Arc::new("test_append_dump.bin".to_string());
+    let journal_file = Arc::new("test_append_journal.txt".to_string());
+    serialize_to_file_with_options(
+        Arc::from(&original_data[..70]),
+        file_path.clone(),
+        journal_file.clone(),
+        &options(),
+    )
+    .await?;
+
+    append_to_file_with_options(
+        Arc::from(&original_data[70..100]),
+        file_path.clone(),
+        &options(),
+    )
+    .await?;
+    append_to_file_with_options(
+        Arc::from(&original_data[100..]),
+        file_path.clone(),
+        &options(),
+    )
+    .await?;
+    append_to_file_with_options(
+        Arc::from(&original_data[..0]),
+        file_path.clone(),
+        &options(),
+    )
+    .await?;
+
+    let retrieved_data = deserialize_from_file::<DailyBlotterData>(file_path.clone()).await?;
+    assert_eq!(*original_data, *retrieved_data);
+    let retrieved_range =
+        deserialize_range_from_file::<DailyBlotterData>(file_path.clone(), 60..110).await?;
+    assert_eq!(original_data[60..110], *retrieved_range);
+
+    // Records of another type are rejected
+    let positions: Arc<[Position]> = Arc::from(vec![Position { account: 1, qty: 2 }]);
+    let err = append_to_file_with_options(positions, file_path.clone(), &options())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::SchemaMismatch { .. }));
+
+    // Clean up test files
+    tokio::fs::remove_file(&*file_path).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_append_crash_keeps_file_readable() -> Result<(), Box<dyn std::error::Error>> {
+    let original_data = DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?;
+
+    let file_path = Arc::new("test_append_crash_dump.bin".to_string());
+    let journal_file = Arc::new("test_append_crash_journal.txt".to_string());
+    serialize_to_file_with_options(
+        Arc::from(&original_data[..70]),
+        file_path.clone(),
+        journal_file.clone(),
+        &options(),
+    )
+    .await?;
+
+    // Crash at every checkpoint in turn: the file keeps the records it held
+    // before the append until one gets through
+    let appended: Arc<[DailyBlotterData]> = Arc::from(&original_data[70..]);
+    let mut steps = 0;
+    loop {
+        failpoint::arm(&file_path, steps);
+        let outcome =
+            append_to_file_with_options(Arc::clone(&appended), file_path.clone(), &options()).await;
+        failpoint::disarm(&file_path);
+        let data = deserialize_from_file::<DailyBlotterData>(file_path.clone()).await?;
+        match outcome {
+            Ok(()) => {
+                assert_eq!(*original_data, *data);
+                break;
+            }
+            Err(err) => {
+                assert!(failpoint::is_injected(&err), "Unexpected error: {}", err);
+                assert_eq!(original_data[..70], *data);
+                let range =
+                    deserialize_range_from_file::<DailyBlotterData>(file_path.clone(), 10..60)
+                        .await?;
+                assert_eq!(original_data[10..60], *range);
+            }
+        }
+        steps += 1;
+    }
+    assert!(steps > 5);
+
+    // Clean up test files
+    tokio::fs::remove_file(&*file_path).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
+
+    Ok(())
+}
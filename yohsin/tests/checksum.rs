@@ -0,0 +1,132 @@
+use std::sync::Arc;
+use yohsin::chunk::{ChunkEntry, ChunkTable};
+use yohsin::compression::Compression;
+use yohsin::header::FileHeader;
+use yohsin::order_struct::DailyBlotterData;
+use yohsin::serialize::{
+    deserialize_from_file, deserialize_range_from_file, serialize_to_file_with_options,
+    SerializeOptions,
+};
+use yohsin::Error;
+
+#[tokio::test]
+async fn test_checksums_detect_corruption() -> Result<(), Box<dyn std::error::Error>> {
+    let original_data = DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?;
+
+    let file_path = Arc::new("test_checksum_dump.bin".to_string());
+    let journal_file = Arc::new("test_checksum_journal.txt".to_string());
+    let options = SerializeOptions {
+        chunk_records: 10,
+        ..SerializeOptions::default()
+    };
+    serialize_to_file_with_options(
+        Arc::clone(&original_data),
+        file_path.clone(),
+        journal_file.clone(),
+        &options,
+    )
+    .await?;
+
+    let complete = tokio::fs::read(&*file_path).await?;
+    let header = FileHeader::decode(&complete)?;
+    let header_len = header.encode().len();
+    let table_offset = header.chunk_table_offset as usize;
+    let table = ChunkTable::decode(&complete[table_offset..], &complete[..header_len])?;
+
+    // Flip one bit in the middle of chunk 3
+    let entry = table.entries[3];
+    let mut corrupted = complete.clone();
+    corrupted[(entry.offset + entry.len / 2) as usize] ^= 0x10;
+    tokio::fs::write(&*file_path, &corrupted).await?;
+
+    let err = deserialize_from_file::<DailyBlotterData>(file_path.clone())
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::ChecksumMismatch { chunk: 3, records, .. } if records == (30..40)
+    ));
+
+    // Ranges only check the chunks they touch
+    let retrieved_range =
+        deserialize_range_from_file::<DailyBlotterData>(file_path.clone(), 5..30).await?;
+    assert_eq!(original_data[5..30], *retrieved_range);
+    let err = deserialize_range_from_file::<DailyBlotterData>(file_path.clone(), 25..35)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::ChecksumMismatch { chunk: 3, .. }));
+
+    // Damage to the chunk table or to the header (here its creation time) is
+    // caught by the table checksum
+    for position in [table_offset + 13, 50] {
+        let mut corrupted = complete.clone();
+        corrupted[position] ^= 0x01;
+        tokio::fs::write(&*file_path, &corrupted).await?;
+        let err = deserialize_range_from_file::<DailyBlotterData>(file_path.clone(), 0..1)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ChunkTableChecksumMismatch { .. }));
+    }
+
+    // Clean up test files
+    tokio::fs::remove_file(&*file_path).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_forged_counts_are_reported() -> Result<(), Box<dyn std::error::Error>> {
+    let original_data = DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?;
+
+    let file_path = Arc::new("test_checksum_forged_dump.bin".to_string());
+    let journal_file = Arc::new("test_checksum_forged_journal.txt".to_string());
+    let options = SerializeOptions {
+        compression: Compression::Lz4,
+        ..SerializeOptions::default()
+    };
+    serialize_to_file_with_options(
+        Arc::from(&original_data[..10]),
+        file_path.clone(),
+        journal_file.clone(),
+        &options,
+    )
+    .await?;
+    let complete = tokio::fs::read(&*file_path).await?;
+    let mut header = FileHeader::decode(&complete)?;
+
+    // A compressed chunk announcing far more records than it holds, with
+    // checksums to match
+    let mut chunk = vec![0];
+    chunk.extend(Compression::Lz4.compress(u32::MAX.to_le_bytes().to_vec()));
+    let header_len = header.encode().len() as u64;
+    header.record_count = u32::MAX as u64;
+    header.chunk_table_offset = header_len + chunk.len() as u64;
+    let header = header.encode();
+    let table = ChunkTable {
+        entries: vec![ChunkEntry {
+            offset: header_len,
+            len: chunk.len() as u64,
+            first_record: 0,
+            record_count: u32::MAX,
+            crc: crc32c::crc32c(&chunk),
+        }],
+    };
+    let forged = [header.clone(), chunk, table.encode(&header)].concat();
+    tokio::fs::write(&*file_path, &forged).await?;
+
+    let err = deserialize_from_file::<DailyBlotterData>(file_path.clone())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Truncated { what: "chunk" }), "{}", err);
+    let err = deserialize_range_from_file::<DailyBlotterData>(file_path.clone(), 5..10)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Truncated { what: "chunk" }), "{}", err);
+
+    // Clean up test files
+    tokio::fs::remove_file(&*file_path).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
+
+    Ok(())
+}
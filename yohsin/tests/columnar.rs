@@ -0,0 +1,421 @@
+use futures::TryStreamExt;
+use std::sync::Arc;
+use yohsin::chunk::{ChunkTable, Layout};
+use yohsin::columnar::{
+    column_stats, ColumnDirectory, ColumnStats, DELTA, DICTIONARY, FRAME_OF_REFERENCE, PLAIN,
+};
+use yohsin::compression::Compression;
+use yohsin::decimal::Price;
+use yohsin::filter::{deserialize_where, Predicate};
+use yohsin::header::FileHeader;
+use yohsin::index::{index_path, lookup_by_key};
+use yohsin::integer;
+use yohsin::mapped::MappedFile;
+use yohsin::order_struct::DailyBlotterData;
+use yohsin::project::deserialize_projected;
+use yohsin::record::FieldType;
+use yohsin::serialize::{
+    append_to_file_with_options, deserialize_from_file, deserialize_range_from_file, iter_records,
+    serialize_to_file_with_options, stream_records, SerializeOptions,
+};
+use yohsin::writer::RecordWriter;
+use yohsin::{Error, Record};
+
+#[derive(Debug, PartialEq, Clone, Record)]
+struct Fill {
+    orderid: String,
+    qtyexec: i64,
+    priceexec: Price,
+}
+
+fn options() -> SerializeOptions {
+    SerializeOptions {
+        chunk_records: 16,
+        layout: Layout::Columns,
+        ..SerializeOptions::default()
+    }
+}
+
+#[tokio::test]
+async fn test_columnar_row_api() -> Result<(), Box<dyn std::error::Error>> {
+    let original_data = DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?;
+
+    let file_path = Arc::new("test_columnar_dump.bin".to_string());
+    let journal_file = Arc::new("test_columnar_journal.txt".to_string());
+    let options = SerializeOptions {
+        index_fields: vec!["orderid"],
+        ..options()
+    };
+    serialize_to_file_with_options(
+        Arc::from(&original_data[..100]),
+        file_path.clone(),
+        journal_file.clone(),
+        &options,
+    )
+    .await?;
+    let bytes = tokio::fs::read(&*file_path).await?;
+    assert_eq!(FileHeader::decode(&bytes)?.layout, Layout::Columns);
+
+    let deserialized = deserialize_from_file::<DailyBlotterData>(file_path.clone()).await?;
+    assert_eq!(original_data[..100], *deserialized);
+    let range = deserialize_range_from_file::<DailyBlotterData>(file_path.clone(), 30..70).await?;
+    assert_eq!(original_data[30..70], *range);
+    let streamed: Vec<DailyBlotterData> = stream_records(file_path.clone()).try_collect().await?;
+    assert_eq!(original_data[..100], *streamed);
+    let iterated = iter_records::<DailyBlotterData>(&file_path)?.collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(original_data[..100], *iterated);
+    let found =
+        lookup_by_key::<DailyBlotterData>(&file_path, "orderid", &original_data[42].orderid)
+            .await?;
+    assert!(found.contains(&original_data[42]));
+
+    // Appending keeps the layout of the file, whatever the options
+    append_to_file_with_options(
+        Arc::from(&original_data[100..]),
+        file_path.clone(),
+        &SerializeOptions::default(),
+    )
+    .await?;
+    // SAFETY: nothing writes to the file until it is dropped
+    let mut mapped = unsafe { MappedFile::<DailyBlotterData>::open(&file_path)? };
+    assert_eq!(mapped.header().layout, Layout::Columns);
+    let viewed = mapped
+        .iter()
+        .map(|view| view.map(DailyBlotterData::from_view))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(original_data[..], *viewed);
+
+    // Or one chunk at a time, each rewritten in the row layout while read
+    mapped.evict();
+    let mut scanned = Vec::new();
+    for chunk in mapped.chunks() {
+        let chunk = chunk?;
+        for view in chunk.iter() {
+            scanned.push(DailyBlotterData::from_view(view?));
+        }
+    }
+    assert_eq!(original_data[..], *scanned);
+    drop(mapped);
+
+    // Written record by record
+    let writer_path = Arc::new("test_columnar_writer_dump.bin".to_string());
+    let writer_journal = Arc::new("test_columnar_writer_journal.txt".to_string());
+    let mut writer =
+        RecordWriter::create_with_options(writer_path.clone(), writer_journal.clone(), &options)
+            .await?;
+    writer.extend(original_data.iter().cloned()).await?;
+    writer.finish().await?;
+    let written = deserialize_from_file::<DailyBlotterData>(writer_path.clone()).await?;
+    assert_eq!(original_data[..], *written);
+
+    // Clean up test files
+    tokio::fs::remove_file(index_path(&file_path, "orderid")).await?;
+    tokio::fs::remove_file(&*file_path).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
+    tokio::fs::remove_file(&*writer_path).await?;
+    tokio::fs::remove_file(&*writer_journal).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_columnar_scans() -> Result<(), Box<dyn std::error::Error>> {
+    let mut original_data =
+        DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?.to_vec();
+
+    // The fixture has a single order time: spread the orders over 25 minutes
+    for (idx, record) in original_data.iter_mut().enumerate() {
+        record.ordertime += 10 * idx as i64;
+    }
+
+    let file_path = Arc::new("test_columnar_scan_dump.bin".to_string());
+    let journal_file = Arc::new("test_columnar_scan_journal.txt".to_string());
+    serialize_to_file_with_options(
+        Arc::from(original_data.clone()),
+        file_path.clone(),
+        journal_file.clone(),
+        &options(),
+    )
+    .await?;
+
+    // Statistics of every row group
+    let stats = column_stats::<DailyBlotterData>(&file_path, "ordertime").await?;
+    let expected: Vec<ColumnStats> = original_data
+        .chunks(16)
+        .map(|records| ColumnStats::Int {
+            min: records.iter().map(|record| record.ordertime).min().unwrap(),
+            max: records.iter().map(|record| record.ordertime).max().unwrap(),
+        })
+        .collect();
+    assert_eq!(expected, stats);
+    let stats = column_stats::<DailyBlotterData>(&file_path, "orderid").await?;
+    let ColumnStats::Str { min, max } = &stats[0] else {
+        panic!("orderid is a string column: {:?}", stats[0]);
+    };
+    assert!(original_data[..16]
+        .iter()
+        .all(|record| min <= &record.orderid && &record.orderid <= max));
+    let err = column_stats::<DailyBlotterData>(&file_path, "venue")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::UnknownField { .. }));
+
+    // Damage a column of row group 0 that neither scan below reads
+    let complete = tokio::fs::read(&*file_path).await?;
+    let header = FileHeader::decode(&complete)?;
+    let header_len = header.encode().len();
+    let table_offset = header.chunk_table_offset as usize;
+    let table = ChunkTable::decode(&complete[table_offset..], &complete[..header_len])?;
+    let types: Vec<_> = header.fields.iter().map(|(_, ty)| *ty).collect();
+    let entry = table.entries[0];
+    let directory = ColumnDirectory::decode(&complete[entry.offset as usize..], &types)?;
+    let symbol = header
+        .fields
+        .iter()
+        .position(|(name, _)| name == "symbol")
+        .unwrap();
+    let column = &directory.columns[symbol];
+    let mut corrupted = complete.clone();
+    corrupted[(entry.offset + column.offset as u64) as usize] ^= 0x10;
+    tokio::fs::write(&*file_path, &corrupted).await?;
+
+    // Only the columns of the projection are read
+    let fills = deserialize_projected::<DailyBlotterData, Fill>(file_path.clone()).await?;
+    let expected: Vec<Fill> = original_data
+        .iter()
+        .map(|record| Fill {
+            orderid: record.orderid.clone(),
+            qtyexec: record.qtyexec,
+            priceexec: record.priceexec,
+        })
+        .collect();
+    assert_eq!(expected, *fills);
+
+    // Row groups ruled out by their statistics are not read
+    let predicate = Predicate::eq("ordertime", &original_data[140].ordertime.to_string());
+    let found = deserialize_where::<DailyBlotterData>(file_path.clone(), &predicate).await?;
+    assert_eq!(original_data[140..141], *found);
+    let err = deserialize_from_file::<DailyBlotterData>(file_path.clone())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::ChecksumMismatch { chunk: 0, .. }));
+
+    // Columns are checked on their own when they are read
+    let err = deserialize_projected::<DailyBlotterData, DailyBlotterData>(file_path.clone())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::ChecksumMismatch { chunk: 0, .. }));
+
+    // Only columnar files have column statistics
+    tokio::fs::write(&*file_path, &complete).await?;
+    serialize_to_file_with_options(
+        Arc::from(original_data.clone()),
+        file_path.clone(),
+        journal_file.clone(),
+        &SerializeOptions::default(),
+    )
+    .await?;
+    let err = column_stats::<DailyBlotterData>(&file_path, "ordertime")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::UnsupportedLayout(Layout::Rows)));
+
+    // Clean up test files
+    tokio::fs::remove_file(&*file_path).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_columnar_dictionary() -> Result<(), Box<dyn std::error::Error>> {
+    let mut original_data =
+        DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?.to_vec();
+
+    // The fixture only sells: buy every third order
+    for record in original_data.iter_mut().step_by(3) {
+        record.side = "BUY".to_string();
+    }
+
+    let file_path = Arc::new("test_columnar_dictionary_dump.bin".to_string());
+    let journal_file = Arc::new("test_columnar_dictionary_journal.txt".to_string());
+    serialize_to_file_with_options(
+        Arc::from(original_data.clone()),
+        file_path.clone(),
+        journal_file.clone(),
+        &options(),
+    )
+    .await?;
+
+    // Low-cardinality strings get a dictionary, unique ones do not
+    let complete = tokio::fs::read(&*file_path).await?;
+    let header = FileHeader::decode(&complete)?;
+    let header_len = header.encode().len();
+    let table_offset = header.chunk_table_offset as usize;
+    let table = ChunkTable::decode(&complete[table_offset..], &complete[..header_len])?;
+    let types: Vec<_> = header.fields.iter().map(|(_, ty)| *ty).collect();
+    let position = |field: &str| {
+        header
+            .fields
+            .iter()
+            .position(|(name, _)| name == field)
+            .unwrap()
+    };
+    for entry in &table.entries {
+        let directory = ColumnDirectory::decode(&complete[entry.offset as usize..], &types)?;
+        for field in ["side", "action", "dest", "tif"] {
+            let column = &directory.columns[position(field)];
+            assert_eq!(column.encoding, DICTIONARY, "{}", field);
+            // Entries, then a byte per record
+            assert!((column.len as usize) < 4 + 2 * (4 + 4) + 16, "{}", field);
+        }
+        assert_eq!(directory.columns[position("orderid")].encoding, PLAIN);
+    }
+
+    let deserialized = deserialize_from_file::<DailyBlotterData>(file_path.clone()).await?;
+    assert_eq!(original_data, *deserialized);
+
+    // Filters compare the codes of the dictionary
+    let side = |side: &str| -> Vec<DailyBlotterData> {
+        original_data
+            .iter()
+            .filter(|record| record.side == side)
+            .cloned()
+            .collect()
+    };
+    for value in ["BUY", "SELL", "SHORT"] {
+        let predicate = Predicate::eq("side", value);
+        let found = deserialize_where::<DailyBlotterData>(file_path.clone(), &predicate).await?;
+        assert_eq!(side(value), *found);
+    }
+    let predicate = Predicate::eq("side", "BUY").and(Predicate::eq("isblotter", "true"));
+    let found = deserialize_where::<DailyBlotterData>(file_path.clone(), &predicate).await?;
+    let expected: Vec<_> = side("BUY")
+        .into_iter()
+        .filter(|record| record.isblotter)
+        .collect();
+    assert_eq!(expected, *found);
+
+    // Clean up test files
+    tokio::fs::remove_file(&*file_path).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_columnar_integer_encodings() -> Result<(), Box<dyn std::error::Error>> {
+    let mut original_data =
+        DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?.to_vec();
+
+    // Spaced ids, one order every few seconds, and a few huge quantities
+    for (idx, record) in original_data.iter_mut().enumerate() {
+        record.id = 1_000_000 + 1000 * idx as i64;
+        record.ordertime += 3 * idx as i64 + idx as i64 % 2;
+    }
+    original_data[7].qtyexec = i64::MIN;
+    original_data[8].qtyexec = i64::MAX;
+    original_data[20].cumqty = i32::MIN;
+
+    let file_path = Arc::new("test_columnar_integer_dump.bin".to_string());
+    let journal_file = Arc::new("test_columnar_integer_journal.txt".to_string());
+    serialize_to_file_with_options(
+        Arc::from(original_data.clone()),
+        file_path.clone(),
+        journal_file.clone(),
+        &options(),
+    )
+    .await?;
+
+    let complete = tokio::fs::read(&*file_path).await?;
+    let header = FileHeader::decode(&complete)?;
+    let header_len = header.encode().len();
+    let table_offset = header.chunk_table_offset as usize;
+    let table = ChunkTable::decode(&complete[table_offset..], &complete[..header_len])?;
+    let types: Vec<_> = header.fields.iter().map(|(_, ty)| *ty).collect();
+    let position = |field: &str| {
+        header
+            .fields
+            .iter()
+            .position(|(name, _)| name == field)
+            .unwrap()
+    };
+    let directory = ColumnDirectory::decode(&complete[table.entries[1].offset as usize..], &types)?;
+    let column = |field: &str| &directory.columns[position(field)];
+    // A single order date: every offset from the minimum takes no bits
+    assert_eq!(column("orderdate").encoding, FRAME_OF_REFERENCE);
+    assert_eq!(column("orderdate").len, 9);
+    // Deltas of 3 and 4 take a byte each
+    assert_eq!(column("ordertime").encoding, DELTA);
+    assert_eq!(column("ordertime").len as usize, 5 + 15);
+    // Steps of 1000 take 2 bytes, offsets up to 15000 take 14 bits
+    assert_eq!(column("id").encoding, DELTA);
+    // Never larger than 16 plain values
+    for (column, ty) in directory.columns.iter().zip(&types) {
+        if matches!(ty, FieldType::I64 | FieldType::I32) {
+            assert!(column.len as usize <= 16 * ty.slot_len());
+        }
+    }
+
+    // Whatever the encoding, values round-trip, extremes included
+    let deserialized = deserialize_from_file::<DailyBlotterData>(file_path.clone()).await?;
+    assert_eq!(original_data, *deserialized);
+    let fills = deserialize_projected::<DailyBlotterData, Fill>(file_path.clone()).await?;
+    assert_eq!(fills[7].qtyexec, i64::MIN);
+    assert_eq!(fills[8].qtyexec, i64::MAX);
+    let predicate = Predicate::eq("id", "1042000");
+    let found = deserialize_where::<DailyBlotterData>(file_path.clone(), &predicate).await?;
+    assert_eq!(original_data[42..43], *found);
+
+    // Compression applies on top of the encodings, chosen as without it
+    for compression in [Compression::Lz4, Compression::Zstd] {
+        let options = SerializeOptions {
+            compression,
+            ..options()
+        };
+        serialize_to_file_with_options(
+            Arc::from(original_data.clone()),
+            file_path.clone(),
+            journal_file.clone(),
+            &options,
+        )
+        .await?;
+        let compressed = tokio::fs::read(&*file_path).await?;
+        let header = FileHeader::decode(&compressed)?;
+        let table_offset = header.chunk_table_offset as usize;
+        let table = ChunkTable::decode(&compressed[table_offset..], &compressed[..header_len])?;
+        let compressed =
+            ColumnDirectory::decode(&compressed[table.entries[1].offset as usize..], &types)?;
+        for (compressed, column) in compressed.columns.iter().zip(&directory.columns) {
+            assert_eq!(compressed.encoding, column.encoding);
+        }
+        let deserialized = deserialize_from_file::<DailyBlotterData>(file_path.clone()).await?;
+        assert_eq!(original_data, *deserialized);
+    }
+
+    for values in [
+        vec![],
+        vec![0],
+        vec![i64::MIN, i64::MAX, 0, -1, 1],
+        (0..100).map(|v| v * v - 50).collect(),
+    ] {
+        let mut delta = Vec::new();
+        integer::encode_delta(&mut delta, &values);
+        assert_eq!(integer::decode_delta(&delta, values.len())?, values);
+        let mut frame = Vec::new();
+        integer::encode_frame_of_reference(&mut frame, &values);
+        assert_eq!(frame.len(), integer::frame_of_reference_len(&values));
+        assert_eq!(
+            integer::decode_frame_of_reference(&frame, values.len())?,
+            values
+        );
+    }
+    let err = integer::decode_delta(&[0x80], 1).unwrap_err();
+    assert!(matches!(err, Error::Truncated { .. }));
+
+    // Clean up test files
+    tokio::fs::remove_file(&*file_path).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
+
+    Ok(())
+}
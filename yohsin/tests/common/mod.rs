@@ -0,0 +1,34 @@
+//! Helpers shared by the crash and resume tests.
+
+use yohsin::chunk::ChunkTable;
+use yohsin::header::FileHeader;
+
+/// Small deterministic generator, so that a failing seed can be replayed.
+pub struct XorShift(pub u64);
+
+impl XorShift {
+    pub fn next(&mut self, bound: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % bound
+    }
+}
+
+/// Bytes of the file with the creation time cleared, the only field allowed
+/// to differ between two runs.
+pub async fn normalized(file_path: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut bytes = tokio::fs::read(file_path).await?;
+    let mut header = FileHeader::decode(&bytes)?;
+    let header_len = header.encode().len();
+    let table_offset = header.chunk_table_offset as usize;
+    let table = ChunkTable::decode(&bytes[table_offset..], &bytes[..header_len])?;
+
+    // The chunk table checksum covers the header, so it changes too
+    header.created_at = 0;
+    let header = header.encode();
+    bytes[..header_len].copy_from_slice(&header);
+    bytes.truncate(table_offset);
+    bytes.extend_from_slice(&table.encode(&header));
+    Ok(bytes)
+}
@@ -0,0 +1,148 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use yohsin::compact::{compact, CompactOptions};
+use yohsin::index::{index_path, lookup_by_key};
+use yohsin::order_struct::DailyBlotterData;
+use yohsin::serialize::{
+    deserialize_from_file, serialize_to_file, serialize_to_file_with_options, SerializeOptions,
+};
+use yohsin::time_index::{deserialize_time_range, time_index_path};
+
+#[tokio::test]
+async fn test_compact_merges_sorts_and_dedups() -> Result<(), Box<dyn std::error::Error>> {
+    let original_data = DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?;
+
+    // Three runs; the second one amends records 50..60 of the first
+    let mut amended = original_data[50..110].to_vec();
+    for record in &mut amended[..10] {
+        record.trader_name = format!("{} (amended)", record.trader_name);
+    }
+    let runs: [(&str, Vec<DailyBlotterData>); 3] = [
+        ("test_compact_0.bin", original_data[..60].to_vec()),
+        ("test_compact_1.bin", amended),
+        ("test_compact_2.bin", original_data[110..].to_vec()),
+    ];
+    let journal_file = Arc::new("test_compact_journal.txt".to_string());
+    for (path, records) in &runs {
+        serialize_to_file(
+            Arc::from(records.clone()),
+            Arc::new(path.to_string()),
+            journal_file.clone(),
+        )
+        .await?;
+    }
+
+    // The latest version of each record, sorted by order id
+    let mut expected: Vec<DailyBlotterData> = Vec::new();
+    let mut seen = HashSet::new();
+    for record in runs.iter().flat_map(|(_, records)| records).rev() {
+        if seen.insert(record.id) {
+            expected.push(record.clone());
+        }
+    }
+    expected.reverse();
+    expected.sort_by(|a, b| a.orderid.cmp(&b.orderid));
+
+    // Compact in place of the first input
+    let inputs: Vec<&str> = runs.iter().map(|(path, _)| *path).collect();
+    let options = CompactOptions::default()
+        .sort_by_key(|record: &DailyBlotterData| record.orderid.clone())
+        .dedup_by_key(|record: &DailyBlotterData| record.id);
+    let written = compact(&inputs, inputs[0], options).await?;
+    assert_eq!(written, expected.len());
+
+    let compacted =
+        deserialize_from_file::<DailyBlotterData>(Arc::new(inputs[0].to_string())).await?;
+    assert_eq!(expected, *compacted);
+    let amended = compacted
+        .iter()
+        .filter(|record| record.trader_name.ends_with("(amended)"));
+    assert_eq!(amended.count(), 10);
+    for input in &inputs[1..] {
+        assert!(tokio::fs::metadata(input).await.is_err());
+    }
+
+    // Clean up test files
+    tokio::fs::remove_file(inputs[0]).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_compact_rebuilds_indexes() -> Result<(), Box<dyn std::error::Error>> {
+    let original_data = DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?;
+
+    let serialize = SerializeOptions {
+        chunk_records: 16,
+        index_fields: vec!["orderid"],
+        time_index_fields: vec![DailyBlotterData::TIME_FIELD],
+        ..SerializeOptions::default()
+    };
+    let inputs = ["test_compact_idx_0.bin", "test_compact_idx_1.bin"];
+    let journal_file = Arc::new("test_compact_idx_journal.txt".to_string());
+    for (path, records) in inputs
+        .iter()
+        .zip([&original_data[..80], &original_data[80..]])
+    {
+        serialize_to_file_with_options(
+            Arc::from(records),
+            Arc::new(path.to_string()),
+            journal_file.clone(),
+            &serialize,
+        )
+        .await?;
+    }
+
+    // In place of the first input, whose indexes become stale
+    let mut options = CompactOptions::default();
+    options.serialize = serialize;
+    let written = compact::<DailyBlotterData>(&inputs, inputs[0], options).await?;
+    assert_eq!(written, original_data.len());
+
+    for record in [&original_data[3], &original_data[120]] {
+        let found =
+            lookup_by_key::<DailyBlotterData>(inputs[0], "orderid", &record.orderid).await?;
+        assert_eq!(found, std::slice::from_ref(record));
+    }
+    let ordertime = original_data[0].ordertime;
+    let in_range = deserialize_time_range::<DailyBlotterData>(
+        Arc::new(inputs[0].to_string()),
+        DailyBlotterData::TIME_FIELD,
+        ordertime,
+        ordertime + 1,
+    )
+    .await?;
+    let expected: Vec<_> = original_data
+        .iter()
+        .filter(|record| record.ordertime == ordertime)
+        .cloned()
+        .collect();
+    assert_eq!(expected, *in_range);
+
+    // Nothing is left under the temporary name, nor of the removed input
+    let tmp = format!("{}.compact", inputs[0]);
+    for leftover in [
+        tmp.clone(),
+        format!("{}.journal", tmp),
+        index_path(&tmp, "orderid"),
+        time_index_path(&tmp, DailyBlotterData::TIME_FIELD),
+        inputs[1].to_string(),
+        index_path(inputs[1], "orderid"),
+        time_index_path(inputs[1], DailyBlotterData::TIME_FIELD),
+    ] {
+        assert!(
+            tokio::fs::metadata(&leftover).await.is_err(),
+            "{}",
+            leftover
+        );
+    }
+
+    // Clean up test files
+    tokio::fs::remove_file(index_path(inputs[0], "orderid")).await?;
+    tokio::fs::remove_file(time_index_path(inputs[0], DailyBlotterData::TIME_FIELD)).await?;
+    tokio::fs::remove_file(inputs[0]).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
+
+    Ok(())
+}
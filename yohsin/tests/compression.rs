@@ -0,0 +1,158 @@
+use futures::TryStreamExt;
+use std::sync::Arc;
+use yohsin::chunk::{ChunkTable, Layout};
+use yohsin::columnar::ColumnDirectory;
+use yohsin::compression::{Compression, STORED};
+use yohsin::header::FileHeader;
+use yohsin::mapped::MappedFile;
+use yohsin::order_struct::DailyBlotterData;
+use yohsin::serialize::{
+    append_to_file_with_options, deserialize_from_file, deserialize_range_from_file,
+    serialize_to_file_with_options, stream_records, SerializeOptions,
+};
+use yohsin::writer::RecordWriter;
+use yohsin::{Error, Record};
+
+#[derive(Debug, PartialEq, Clone, Record)]
+struct Tick {
+    id: i64,
+}
+
+#[tokio::test]
+async fn test_compressed_files() -> Result<(), Box<dyn std::error::Error>> {
+    let original_data = DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?;
+
+    let file_path = Arc::new("test_compression_dump.bin".to_string());
+    let journal_file = Arc::new("test_compression_journal.txt".to_string());
+    for layout in [Layout::Rows, Layout::Columns] {
+        let mut uncompressed_len = 0;
+        for compression in [Compression::None, Compression::Lz4, Compression::Zstd] {
+            let options = SerializeOptions {
+                chunk_records: 16,
+                layout,
+                compression,
+                ..SerializeOptions::default()
+            };
+            serialize_to_file_with_options(
+                Arc::from(&original_data[..100]),
+                file_path.clone(),
+                journal_file.clone(),
+                &options,
+            )
+            .await?;
+            append_to_file_with_options(
+                Arc::from(&original_data[100..]),
+                file_path.clone(),
+                &SerializeOptions::default(),
+            )
+            .await?;
+
+            // Recorded in the header, and kept by appends
+            let complete = tokio::fs::read(&*file_path).await?;
+            let header = FileHeader::decode(&complete)?;
+            assert_eq!(header.compression, compression);
+            match compression {
+                Compression::None => uncompressed_len = complete.len(),
+                _ => assert!(
+                    complete.len() < uncompressed_len,
+                    "{:?} {:?}",
+                    layout,
+                    compression
+                ),
+            }
+
+            let deserialized = deserialize_from_file::<DailyBlotterData>(file_path.clone()).await?;
+            assert_eq!(original_data[..], *deserialized);
+            let streamed: Vec<DailyBlotterData> =
+                stream_records(file_path.clone()).try_collect().await?;
+            assert_eq!(original_data[..], *streamed);
+            // SAFETY: nothing writes to the file until it is dropped
+            let mapped = unsafe { MappedFile::<DailyBlotterData>::open(&file_path)? };
+            assert_eq!(
+                DailyBlotterData::from_view(mapped.get(123)?),
+                original_data[123]
+            );
+            drop(mapped);
+
+            // Range reads only decompress the chunks they touch: damage to
+            // chunk 0 goes unnoticed
+            let header_len = header.encode().len();
+            let table_offset = header.chunk_table_offset as usize;
+            let table = ChunkTable::decode(&complete[table_offset..], &complete[..header_len])?;
+            let entry = table.entries[0];
+
+            // Columns that would not shrink, such as the random strings of
+            // the fixture under LZ4, are stored as is
+            if layout == Layout::Columns {
+                let types: Vec<_> = header.fields.iter().map(|(_, ty)| *ty).collect();
+                let directory =
+                    ColumnDirectory::decode(&complete[entry.offset as usize..], &types)?;
+                let stored = directory.columns.iter().filter(|c| c.stored).count();
+                match compression {
+                    Compression::None => assert_eq!(stored, 0),
+                    Compression::Lz4 => assert!(0 < stored && stored < directory.columns.len()),
+                    Compression::Zstd => assert!(stored < directory.columns.len()),
+                }
+            } else if compression != Compression::None {
+                assert_eq!(complete[entry.offset as usize], 0);
+            }
+
+            let mut corrupted = complete.clone();
+            corrupted[(entry.offset + entry.len - 1) as usize] ^= 0x10;
+            tokio::fs::write(&*file_path, &corrupted).await?;
+            let range =
+                deserialize_range_from_file::<DailyBlotterData>(file_path.clone(), 40..90).await?;
+            assert_eq!(original_data[40..90], *range);
+            let err = deserialize_range_from_file::<DailyBlotterData>(file_path.clone(), 10..20)
+                .await
+                .unwrap_err();
+            assert!(matches!(err, Error::ChecksumMismatch { chunk: 0, .. }));
+        }
+    }
+
+    // So are row chunks, behind their flags byte: LZ4 cannot shrink a lone
+    // integer
+    let ticks: Vec<Tick> = (0..5).map(|id| Tick { id }).collect();
+    let options = SerializeOptions {
+        chunk_records: 1,
+        compression: Compression::Lz4,
+        ..SerializeOptions::default()
+    };
+    serialize_to_file_with_options(
+        Arc::from(ticks.as_slice()),
+        file_path.clone(),
+        journal_file.clone(),
+        &options,
+    )
+    .await?;
+    let complete = tokio::fs::read(&*file_path).await?;
+    let header = FileHeader::decode(&complete)?;
+    let header_len = header.encode().len();
+    let table_offset = header.chunk_table_offset as usize;
+    let table = ChunkTable::decode(&complete[table_offset..], &complete[..header_len])?;
+    for entry in &table.entries {
+        assert_eq!(complete[entry.offset as usize], STORED);
+    }
+    let deserialized = deserialize_from_file::<Tick>(file_path.clone()).await?;
+    assert_eq!(ticks[..], *deserialized);
+
+    // Written record by record
+    let options = SerializeOptions {
+        chunk_records: 16,
+        compression: Compression::Lz4,
+        ..SerializeOptions::default()
+    };
+    let mut writer =
+        RecordWriter::create_with_options(file_path.clone(), journal_file.clone(), &options)
+            .await?;
+    writer.extend(original_data.iter().cloned()).await?;
+    writer.finish().await?;
+    let written = deserialize_from_file::<DailyBlotterData>(file_path.clone()).await?;
+    assert_eq!(original_data[..], *written);
+
+    // Clean up test files
+    tokio::fs::remove_file(&*file_path).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
+
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use std::sync::Arc;
+use yohsin::chunk::ChunkTable;
+use yohsin::header::FileHeader;
+use yohsin::order_struct::DailyBlotterData;
+use yohsin::serialize::{
+    deserialize_from_file, deserialize_range_from_file, serialize_to_file_with_options,
+    SerializeOptions,
+};
+
+/// Serializes `data` on `threads` tasks and returns the header without its
+/// creation time, which is the only field allowed to differ, the chunk table
+/// and the bytes of the chunks.
+async fn serialize_on(
+    data: &Arc<[DailyBlotterData]>,
+    threads: usize,
+) -> Result<(FileHeader, ChunkTable, Vec<u8>), Box<dyn std::error::Error>> {
+    let file_path = Arc::new(format!("test_concurrent_{}_dump.bin", threads));
+    let journal_file = Arc::new(format!("test_concurrent_{}_journal.txt", threads));
+    let options = SerializeOptions {
+        chunk_records: 7,
+        threads,
+        ..SerializeOptions::default()
+    };
+    serialize_to_file_with_options(
+        Arc::clone(data),
+        file_path.clone(),
+        journal_file.clone(),
+        &options,
+    )
+    .await?;
+
+    // Every layout must read back to the original data
+    let retrieved_data = deserialize_from_file::<DailyBlotterData>(file_path.clone()).await?;
+    assert_eq!(**data, *retrieved_data);
+    let retrieved_range =
+        deserialize_range_from_file::<DailyBlotterData>(file_path.clone(), 5..66).await?;
+    assert_eq!(data[5..66], *retrieved_range);
+
+    let bytes = tokio::fs::read(&*file_path).await?;
+    let mut header = FileHeader::decode(&bytes)?;
+    let header_len = header.encode().len();
+    let table_offset = header.chunk_table_offset as usize;
+    let table = ChunkTable::decode(&bytes[table_offset..], &bytes[..header_len])?;
+    header.created_at = 0;
+
+    // Clean up test files
+    tokio::fs::remove_file(&*file_path).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
+
+    // The chunk table checksum covers the header, compare the entries instead
+    Ok((header, table, bytes[header_len..table_offset].to_vec()))
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn test_output_independent_of_threads() -> Result<(), Box<dyn std::error::Error>> {
+    let original_data = DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?;
+
+    let expected = serialize_on(&original_data, 1).await?;
+    for _ in 0..5 {
+        for threads in [2, 3, 8, 22, 64] {
+            let written = serialize_on(&original_data, threads).await?;
+            assert!(
+                expected == written,
+                "File written on {} threads differs from the single-threaded one",
+                threads
+            );
+        }
+    }
+
+    Ok(())
+}
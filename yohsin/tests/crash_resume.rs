@@ -0,0 +1,92 @@
+mod common;
+
+use common::{normalized, XorShift};
+use std::sync::Arc;
+use yohsin::failpoint;
+use yohsin::order_struct::DailyBlotterData;
+use yohsin::serialize::{deserialize_from_file, serialize_to_file_with_options, SerializeOptions};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_crash_and_resume_at_random_points() -> Result<(), Box<dyn std::error::Error>> {
+    let original_data = DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?;
+    let options = |threads| SerializeOptions {
+        chunk_records: 8,
+        threads,
+        ..SerializeOptions::default()
+    };
+
+    // Reference output of an uninterrupted run
+    let reference_path = Arc::new("test_crash_reference_dump.bin".to_string());
+    let reference_journal = Arc::new("test_crash_reference_journal.txt".to_string());
+    serialize_to_file_with_options(
+        Arc::clone(&original_data),
+        reference_path.clone(),
+        reference_journal.clone(),
+        &options(4),
+    )
+    .await?;
+    let expected = normalized(&reference_path).await?;
+
+    let file_path = Arc::new("test_crash_dump.bin".to_string());
+    let journal_file = Arc::new("test_crash_journal.txt".to_string());
+    for seed in 1..=40u64 {
+        let mut rng = XorShift(seed);
+        let _ = tokio::fs::remove_file(&*file_path).await;
+        let _ = tokio::fs::remove_file(&*journal_file).await;
+
+        // Crash at a random checkpoint until a run gets through
+        let mut crashes = 0;
+        loop {
+            failpoint::arm(&file_path, rng.next(48) as usize);
+            let threads = 1 + rng.next(6) as usize;
+            match serialize_to_file_with_options(
+                Arc::clone(&original_data),
+                file_path.clone(),
+                journal_file.clone(),
+                &options(threads),
+            )
+            .await
+            {
+                Ok(()) => break,
+                Err(err) => assert!(
+                    failpoint::is_injected(&err),
+                    "Seed {}: unexpected error: {}",
+                    seed,
+                    err
+                ),
+            }
+            crashes += 1;
+            assert!(
+                crashes < 100,
+                "Seed {}: serialization never completes",
+                seed
+            );
+
+            // A crashed file is either rejected or already complete, never wrong
+            if let Ok(data) = deserialize_from_file::<DailyBlotterData>(file_path.clone()).await {
+                assert_eq!(*original_data, *data, "Seed {}", seed);
+            }
+        }
+        failpoint::disarm(&file_path);
+
+        let retrieved_data = deserialize_from_file::<DailyBlotterData>(file_path.clone()).await?;
+        assert_eq!(*original_data, *retrieved_data, "Seed {}", seed);
+        assert!(
+            normalized(&file_path).await? == expected,
+            "Seed {}: resumed file differs from an uninterrupted run",
+            seed
+        );
+    }
+
+    // Clean up test files
+    for path in [
+        &file_path,
+        &journal_file,
+        &reference_path,
+        &reference_journal,
+    ] {
+        tokio::fs::remove_file(&**path).await?;
+    }
+
+    Ok(())
+}
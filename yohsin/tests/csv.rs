@@ -0,0 +1,199 @@
+use yohsin::csv::{
+    load_from_file, load_from_file_with_options, quote, write_to_file_with_options, CsvOptions,
+    CsvReader, CsvRecord,
+};
+use yohsin::decimal::Price;
+use yohsin::order_struct::DailyBlotterData;
+use yohsin::{Error, Record};
+
+#[derive(Debug, PartialEq, Clone, Record)]
+struct Note {
+    id: i64,
+    text: String,
+    price: Price,
+}
+
+fn tokenize(input: &str, options: &CsvOptions) -> Result<Vec<CsvRecord>, Error> {
+    CsvReader::new(input.as_bytes(), options)?.collect()
+}
+
+fn record(line: usize, fields: &[&str]) -> CsvRecord {
+    CsvRecord {
+        line,
+        fields: fields.iter().map(|field| field.to_string()).collect(),
+    }
+}
+
+#[test]
+fn test_csv_tokenizer() -> Result<(), Box<dyn std::error::Error>> {
+    let input = "id,text,price\r\n\
+                 1,\"buy, then sell\",1.50\r\n\
+                 \r\n\
+                 2,\"say \"\"hi\"\"\",\r\n\
+                 3,\"two\r\nlines\",\"\"\n\
+                 4,plain,7";
+    assert_eq!(
+        tokenize(input, &CsvOptions::default())?,
+        [
+            record(1, &["id", "text", "price"]),
+            record(2, &["1", "buy, then sell", "1.50"]),
+            record(4, &["2", "say \"hi\"", ""]),
+            record(5, &["3", "two\r\nlines", ""]),
+            record(7, &["4", "plain", "7"]),
+        ]
+    );
+
+    let semicolon = CsvOptions { delimiter: b';' };
+    assert_eq!(
+        tokenize("1;a,b;\"c;d\"\n", &semicolon)?,
+        [record(1, &["1", "a,b", "c;d"])]
+    );
+    assert_eq!(quote("a,b", b';'), "a,b");
+    assert_eq!(quote("c;d", b';'), "\"c;d\"");
+    assert_eq!(quote("say \"hi\"", b','), "\"say \"\"hi\"\"\"");
+
+    for (input, line, column, value, reason) in [
+        (
+            "1,ok\n2,\"never closed\n3,x\n",
+            2,
+            2,
+            "never closed",
+            "unterminated quoted value",
+        ),
+        (
+            "1,5\" screen\n",
+            1,
+            2,
+            "5\" screen",
+            "double quote in an unquoted value",
+        ),
+        (
+            "1,\"quoted\"tail,x\n",
+            1,
+            2,
+            "\"quoted\"tail",
+            "unexpected character after a closing quote",
+        ),
+    ] {
+        let err = tokenize(input, &CsvOptions::default()).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                Error::CsvMalformed { line: l, column: c, field: None, value: ref v, reason: r }
+                    if l == line && c == column && v == value && r == reason
+            ),
+            "{}",
+            err
+        );
+    }
+
+    // Delimiters that would make values ambiguous are refused
+    for delimiter in [b'"', b'\n', b'\r', 0xc3] {
+        let options = CsvOptions { delimiter };
+        let err = tokenize("1,a\n", &options).unwrap_err();
+        assert!(matches!(err, Error::InvalidOptions(_)), "{}", err);
+        let err = write_to_file_with_options::<Note>("test_csv_delimiter.csv", &[], &options)
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidOptions(_)), "{}", err);
+    }
+    assert!(std::fs::metadata("test_csv_delimiter.csv").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_csv_load_errors() -> Result<(), Box<dyn std::error::Error>> {
+    let file_path = "test_csv_errors.csv";
+    let load = |contents: &str| {
+        std::fs::write(file_path, contents).unwrap();
+        let result = load_from_file::<Note>(file_path);
+        std::fs::remove_file(file_path).unwrap();
+        result
+    };
+
+    // A short line is reported, not a panic
+    let err = load("id,text,price\n1,a,1.00\n2,b\n").unwrap_err();
+    assert!(matches!(
+        err,
+        Error::CsvFieldCount {
+            line: 3,
+            expected: 3,
+            found: 2
+        }
+    ));
+
+    // Lines are counted through values spanning several
+    let err = load("id,text,price\n1,\"a\nb\",1.00\n2,c,cheap\n").unwrap_err();
+    assert!(matches!(
+        err,
+        Error::CsvParse { line: 4, column: 3, field: "price", ref value, .. } if value == "cheap"
+    ));
+
+    // Malformed values are named after their field
+    let err = load("id,text,price\n1,\"a\"b,1.00\n").unwrap_err();
+    assert!(matches!(
+        err,
+        Error::CsvMalformed {
+            line: 2,
+            column: 2,
+            field: Some("text"),
+            ..
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "Line 2, column 2 (`text`): malformed value \"\\\"a\\\"b\": \
+         unexpected character after a closing quote"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_csv_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+    let mut original_data =
+        DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?.to_vec();
+
+    // Free text may hold anything that needs quoting
+    let texts = [
+        "limit, not market",
+        "client said \"urgent\"",
+        "first line\nsecond line",
+        "windows\r\nline",
+        "semi;colon",
+        "",
+    ];
+    for (record, text) in original_data.iter_mut().zip(texts.iter().cycle()) {
+        record.ordertext = text.to_string();
+    }
+
+    let file_path = "test_csv_round_trip.csv";
+    for delimiter in [b',', b';', b'\t'] {
+        let options = CsvOptions { delimiter };
+        write_to_file_with_options(file_path, &original_data, &options)?;
+        let loaded = load_from_file_with_options::<DailyBlotterData>(file_path, &options)?;
+        assert_eq!(original_data[..], *loaded);
+    }
+
+    // CRLF line endings read the same
+    let options = CsvOptions::default();
+    write_to_file_with_options(file_path, &original_data[..6], &options)?;
+    let crlf = std::fs::read_to_string(file_path)?
+        .split_inclusive('\n')
+        .map(|line| match line.strip_suffix('\n') {
+            // Only the line endings, not the line breaks inside values
+            Some(line) if !line.ends_with("first line") && !line.ends_with("windows\r") => {
+                format!("{}\r\n", line)
+            }
+            _ => line.to_string(),
+        })
+        .collect::<String>();
+    std::fs::write(file_path, crlf)?;
+    let loaded = load_from_file::<DailyBlotterData>(file_path)?;
+    assert_eq!(original_data[..6], *loaded);
+
+    // Clean up test files
+    std::fs::remove_file(file_path)?;
+
+    Ok(())
+}
@@ -0,0 +1,143 @@
+use std::sync::Arc;
+use yohsin::chunk::Layout;
+use yohsin::columnar::{column_stats, ColumnStats};
+use yohsin::decimal::{Decimal, ParseDecimalError, Price};
+use yohsin::filter::{deserialize_where, Predicate};
+use yohsin::header::FileHeader;
+use yohsin::order_struct::DailyBlotterData;
+use yohsin::record::{FieldType, FieldValue};
+use yohsin::serialize::{deserialize_from_file, serialize_to_file_with_options, SerializeOptions};
+use yohsin::Error;
+
+#[test]
+fn test_decimal_parse_and_format() {
+    let price: Price = "18.83".parse().unwrap();
+    assert_eq!(price.mantissa(), 1883);
+    assert_eq!(price.to_string(), "18.83");
+
+    // Printed with exactly SCALE decimals
+    for (raw, canonical) in [
+        ("18.8", "18.80"),
+        ("18", "18.00"),
+        ("+7.05", "7.05"),
+        ("-0.05", "-0.05"),
+        ("-0", "0.00"),
+        ("007.10", "7.10"),
+    ] {
+        assert_eq!(raw.parse::<Price>().unwrap().to_string(), canonical);
+    }
+    assert_eq!(
+        Price::new(i64::MIN).to_string().parse::<Price>(),
+        Ok(Price::new(i64::MIN))
+    );
+    assert_eq!("-12.5".parse::<Decimal<4>>().unwrap().mantissa(), -125_000);
+    assert_eq!("42".parse::<Decimal<0>>().unwrap().to_string(), "42");
+
+    for (raw, err) in [
+        ("", ParseDecimalError::Empty),
+        ("-", ParseDecimalError::Empty),
+        (".5", ParseDecimalError::Empty),
+        ("1.", ParseDecimalError::InvalidDigit),
+        ("1.2.3", ParseDecimalError::InvalidDigit),
+        ("1e3", ParseDecimalError::InvalidDigit),
+        ("NaN", ParseDecimalError::InvalidDigit),
+        (" 1.00", ParseDecimalError::InvalidDigit),
+        (
+            "71.5374416789175",
+            ParseDecimalError::TooPrecise { scale: 2 },
+        ),
+        ("92233720368547758.08", ParseDecimalError::Overflow),
+    ] {
+        assert_eq!(raw.parse::<Price>(), Err(err), "{:?}", raw);
+    }
+    assert_eq!(
+        Price::parse("1.005").unwrap_err(),
+        "more than 2 digits after the point"
+    );
+
+    // The scale is part of the field type, hence of the schema fingerprint
+    assert_eq!(Price::TYPE, FieldType::Decimal(2));
+    assert_eq!(FieldType::from_tag(Price::TYPE.tag()), Some(Price::TYPE));
+    assert_ne!(Decimal::<4>::TYPE.tag(), Price::TYPE.tag());
+}
+
+#[tokio::test]
+async fn test_decimal_round_trips() -> Result<(), Box<dyn std::error::Error>> {
+    let fixture = "../data_baker/data/data-file.csv";
+    let original_data = DailyBlotterData::load_from_file(fixture)?;
+
+    // Writing back the loaded records reproduces every row of the fixture
+    let csv_path = "test_decimal_written.csv";
+    DailyBlotterData::write_to_file(csv_path, &original_data)?;
+    let written = std::fs::read_to_string(csv_path)?;
+    std::fs::remove_file(csv_path)?;
+    let expected = std::fs::read_to_string(fixture)?;
+    assert_eq!(
+        written.lines().skip(1).collect::<Vec<_>>(),
+        expected.lines().skip(1).collect::<Vec<_>>()
+    );
+
+    let file_path = Arc::new("test_decimal_dump.bin".to_string());
+    let journal_file = Arc::new("test_decimal_journal.txt".to_string());
+    for layout in [Layout::Rows, Layout::Columns] {
+        let options = SerializeOptions {
+            chunk_records: 64,
+            layout,
+            ..SerializeOptions::default()
+        };
+        serialize_to_file_with_options(
+            original_data.clone(),
+            file_path.clone(),
+            journal_file.clone(),
+            &options,
+        )
+        .await?;
+
+        let header = FileHeader::decode(&tokio::fs::read(&*file_path).await?)?;
+        assert!(header
+            .fields
+            .iter()
+            .any(|(name, ty)| name == "price" && *ty == FieldType::Decimal(2)));
+
+        let deserialized = deserialize_from_file::<DailyBlotterData>(file_path.clone()).await?;
+        assert_eq!(original_data[..], *deserialized);
+
+        // Predicates are parsed at the scale of the field
+        let price = original_data[42].price;
+        let predicate = Predicate::eq("price", &price.to_string());
+        let found = deserialize_where::<DailyBlotterData>(file_path.clone(), &predicate).await?;
+        assert!(!found.is_empty());
+        assert!(found.iter().all(|record| record.price == price));
+        let predicate = Predicate::eq("price", "18.833");
+        let err = deserialize_where::<DailyBlotterData>(file_path.clone(), &predicate)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidKey { field: "price", .. }));
+    }
+
+    // Column statistics hold the scaled integers
+    let stats = column_stats::<DailyBlotterData>(&file_path, "stopprice").await?;
+    let min = original_data[..64]
+        .iter()
+        .map(|r| r.stopprice)
+        .min()
+        .unwrap();
+    let max = original_data[..64]
+        .iter()
+        .map(|r| r.stopprice)
+        .max()
+        .unwrap();
+    assert_eq!(
+        stats[0],
+        ColumnStats::Int {
+            min: min.mantissa(),
+            max: max.mantissa(),
+        }
+    );
+
+    // Clean up test files
+    tokio::fs::remove_file(&*file_path).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
+
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use std::sync::Arc;
+use yohsin::record::{FieldType, RecordSize};
+use yohsin::serialize::{deserialize_from_file, serialize_to_file};
+use yohsin::{csv, Error, Record};
+
+#[derive(Debug, PartialEq, Clone, Record)]
+struct Fill {
+    fill_id: i64,
+    symbol: String,
+    qty: i32,
+    price: f64,
+    is_final: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Record)]
+struct Position {
+    account: i64,
+    qty: i64,
+}
+
+fn fills() -> Vec<Fill> {
+    (0..100)
+        .map(|i| Fill {
+            fill_id: i,
+            symbol: format!("SYM{}", i % 7),
+            qty: (i * 10) as i32,
+            price: i as f64 / 4.0,
+            is_final: i % 2 == 0,
+        })
+        .collect()
+}
+
+#[test]
+fn test_derived_schema() {
+    let names: Vec<&str> = Fill::SCHEMA.fields.iter().map(|f| f.name).collect();
+    assert_eq!(names, ["fill_id", "symbol", "qty", "price", "is_final"]);
+    assert_eq!(Fill::SCHEMA.fields[1].ty, FieldType::Str);
+    assert_eq!(Fill::record_size(), RecordSize::Variable { min: 29 });
+    assert_eq!(Position::record_size(), RecordSize::Fixed(16));
+}
+
+#[test]
+fn test_derived_csv_mapping() -> Result<(), Box<dyn std::error::Error>> {
+    let fill = Fill::from_csv_fields(&["7", "AAPL", "100", "12.5", "true"])?;
+    assert_eq!(fill.to_csv_fields(), ["7", "AAPL", "100", "12.5", "true"]);
+
+    let err = Fill::from_csv_fields(&["7", "AAPL", "lots", "12.5", "true"]).unwrap_err();
+    assert!(matches!(
+        err,
+        Error::CsvParse { column: 3, field: "qty", ref value, .. } if value == "lots"
+    ));
+    assert!(matches!(
+        Fill::from_csv_fields(&["7", "AAPL"]),
+        Err(Error::CsvFieldCount {
+            expected: 5,
+            found: 2,
+            ..
+        })
+    ));
+
+    // Loading a file reports the line of the faulty value
+    let file_path = "test_derive_bad.csv";
+    std::fs::write(
+        file_path,
+        "fill_id,symbol,qty,price,is_final\n1,AAPL,10,1.5,true\n2,MSFT,10,cheap,false\n",
+    )?;
+    let err = csv::load_from_file::<Fill>(file_path).unwrap_err();
+    std::fs::remove_file(file_path)?;
+    assert!(matches!(
+        err,
+        Error::CsvParse {
+            line: 3,
+            field: "price",
+            ..
+        }
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_derived_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+    let original_data: Arc<[Fill]> = Arc::from(fills());
+
+    let file_path = Arc::new("test_derive_dump.bin".to_string());
+    let journal_file = Arc::new("test_derive_journal.txt".to_string());
+    serialize_to_file(
+        Arc::clone(&original_data),
+        file_path.clone(),
+        journal_file.clone(),
+    )
+    .await?;
+
+    let retrieved_data = deserialize_from_file::<Fill>(file_path.clone()).await?;
+    assert_eq!(*original_data, *retrieved_data);
+
+    // Clean up test files
+    tokio::fs::remove_file(&*file_path).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
+
+    Ok(())
+}
@@ -9,11 +9,11 @@ async fn test_serialize_deserialize() -> Result<(), Box<dyn std::error::Error>>
 
     // Serialize the data to a file
     let file_path = Arc::new("test_dump.bin".to_string());
-    let memo_file = Arc::new("test_memo.txt".to_string());
+    let journal_file = Arc::new("test_journal.txt".to_string());
     serialize_to_file(
         Arc::clone(&original_data),
         file_path.clone(),
-        memo_file.clone(),
+        journal_file.clone(),
     )
     .await?;
 
@@ -28,7 +28,43 @@ async fn test_serialize_deserialize() -> Result<(), Box<dyn std::error::Error>>
 
     // Clean up test files
     tokio::fs::remove_file(&*file_path).await?;
-    tokio::fs::remove_file(&*memo_file).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
+
+    Ok(())
+}
+
+/// Set in the child process spawned by `test_cross_process_round_trip`.
+const CHILD_ENV: &str = "YOHSIN_CROSS_PROCESS_CHILD";
+
+#[tokio::test]
+async fn test_cross_process_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+    let file_path = Arc::new("test_cross_process_dump.bin".to_string());
+    let journal_file = Arc::new("test_cross_process_journal.txt".to_string());
+    let original_data = DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?;
+
+    // In the child only write the file, so that it outlives the process
+    if std::env::var_os(CHILD_ENV).is_some() {
+        serialize_to_file(original_data, file_path, journal_file).await?;
+        return Ok(());
+    }
+
+    let status = std::process::Command::new(std::env::current_exe()?)
+        .args(["test_cross_process_round_trip", "--exact", "--quiet"])
+        .env(CHILD_ENV, "1")
+        .status()?;
+    assert!(status.success(), "Writer process failed: {status}");
+
+    // Deserialize the data written by the other process
+    let retrieved_data = deserialize_from_file::<DailyBlotterData>(file_path.clone()).await?;
+
+    assert_eq!(
+        *original_data, *retrieved_data,
+        "Data written by another process does not match"
+    );
+
+    // Clean up test files
+    tokio::fs::remove_file(&*file_path).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
 
     Ok(())
 }
@@ -9,11 +9,11 @@ async fn test_deserialize_range() -> Result<(), Box<dyn std::error::Error>> {
 
     // Serialize the data to a file
     let file_path = Arc::new("test_dump.bin".to_string());
-    let memo_file = Arc::new("test_memo.txt".to_string());
+    let journal_file = Arc::new("test_journal.txt".to_string());
     serialize_to_file(
         Arc::clone(&original_data),
         file_path.clone(),
-        memo_file.clone(),
+        journal_file.clone(),
     )
     .await?;
 
@@ -36,7 +36,7 @@ async fn test_deserialize_range() -> Result<(), Box<dyn std::error::Error>> {
 
     // Clean up test files
     tokio::fs::remove_file(&*file_path).await?;
-    tokio::fs::remove_file(&*memo_file).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
 
     Ok(())
 }
@@ -0,0 +1,74 @@
+use std::sync::Arc;
+use yohsin::header::{FileHeader, FORMAT_VERSION};
+use yohsin::order_struct::DailyBlotterData;
+use yohsin::serialize::{deserialize_from_file, deserialize_range_from_file, serialize_to_file};
+use yohsin::{Error, Record};
+
+#[derive(Debug, PartialEq, Clone, Record)]
+struct Position {
+    account: i64,
+    qty: i64,
+}
+
+#[tokio::test]
+async fn test_header_describes_records() -> Result<(), Box<dyn std::error::Error>> {
+    let original_data = DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?;
+
+    let file_path = Arc::new("test_header_dump.bin".to_string());
+    let journal_file = Arc::new("test_header_journal.txt".to_string());
+    serialize_to_file(
+        Arc::clone(&original_data),
+        file_path.clone(),
+        journal_file.clone(),
+    )
+    .await?;
+
+    let bytes = tokio::fs::read(&*file_path).await?;
+    let header = FileHeader::decode(&bytes)?;
+    assert_eq!(header.format_version, FORMAT_VERSION);
+    assert_eq!(header.record_count, original_data.len() as u64);
+    assert_eq!(header.record_size, DailyBlotterData::record_size());
+    assert_eq!(header.schema_hash, DailyBlotterData::SCHEMA.fingerprint());
+    assert_eq!(header.fields.len(), 57);
+    assert_eq!(header.writer_version, env!("CARGO_PKG_VERSION"));
+    assert!(header.created_at > 0);
+
+    // Reading the file as another record type is rejected
+    let err = deserialize_from_file::<Position>(file_path.clone())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::SchemaMismatch { .. }));
+    let err = deserialize_range_from_file::<Position>(file_path.clone(), 0..1)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::SchemaMismatch { .. }));
+
+    // Clean up test files
+    tokio::fs::remove_file(&*file_path).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_header_rejects_foreign_files() -> Result<(), Box<dyn std::error::Error>> {
+    let file_path = Arc::new("test_header_foreign.bin".to_string());
+
+    // A bare record count, as written by earlier versions
+    tokio::fs::write(&*file_path, 150u64.to_le_bytes().repeat(4)).await?;
+    let err = deserialize_from_file::<DailyBlotterData>(file_path.clone())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::BadMagic(_)));
+
+    // A file cut in the middle of the header
+    tokio::fs::write(&*file_path, b"YOHSIN\0\0\x01").await?;
+    let err = deserialize_range_from_file::<DailyBlotterData>(file_path.clone(), 0..1)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Truncated { what: "header" }));
+
+    tokio::fs::remove_file(&*file_path).await?;
+
+    Ok(())
+}
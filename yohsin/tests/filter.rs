@@ -0,0 +1,83 @@
+use std::sync::Arc;
+use yohsin::filter::{deserialize_filtered, deserialize_where, Predicate};
+use yohsin::order_struct::{DailyBlotterData, DailyBlotterDataView};
+use yohsin::serialize::{serialize_to_file_with_options, SerializeOptions};
+use yohsin::Error;
+
+#[tokio::test]
+async fn test_filter_during_deserialization() -> Result<(), Box<dyn std::error::Error>> {
+    let mut original_data =
+        DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?.to_vec();
+
+    // The fixture has a random symbol per order and a single side
+    for (idx, record) in original_data.iter_mut().enumerate() {
+        if idx % 3 == 0 {
+            record.symbol = "AAPL".to_string();
+        }
+        if idx % 2 == 0 {
+            record.side = "BUY".to_string();
+        }
+    }
+
+    let file_path = Arc::new("test_filter_dump.bin".to_string());
+    let journal_file = Arc::new("test_filter_journal.txt".to_string());
+    let options = SerializeOptions {
+        chunk_records: 16,
+        ..SerializeOptions::default()
+    };
+    serialize_to_file_with_options(
+        Arc::from(original_data.clone()),
+        file_path.clone(),
+        journal_file.clone(),
+        &options,
+    )
+    .await?;
+
+    let expected: Vec<DailyBlotterData> = original_data
+        .iter()
+        .filter(|record| record.symbol == "AAPL" && record.side == "BUY")
+        .cloned()
+        .collect();
+    assert_eq!(expected.len(), 25);
+
+    let predicate = Predicate::eq("symbol", "AAPL").and(Predicate::eq("side", "BUY"));
+    let found = deserialize_where::<DailyBlotterData>(file_path.clone(), &predicate).await?;
+    assert_eq!(expected, *found);
+
+    let found = deserialize_filtered(file_path.clone(), |view: DailyBlotterDataView| {
+        view.symbol == "AAPL" && view.side == "BUY"
+    })
+    .await?;
+    assert_eq!(expected, *found);
+
+    // Scalar fields, and alternatives
+    let record = &original_data[77];
+    let predicate =
+        Predicate::eq("id", &record.id.to_string())
+            .or(Predicate::eq("isblotter", "true").and(Predicate::eq("symbol", "AAPL")));
+    let found = deserialize_where::<DailyBlotterData>(file_path.clone(), &predicate).await?;
+    let expected: Vec<DailyBlotterData> = original_data
+        .iter()
+        .filter(|other| other.id == record.id || (other.isblotter && other.symbol == "AAPL"))
+        .cloned()
+        .collect();
+    assert_eq!(expected, *found);
+
+    // Predicates are checked against the schema before reading
+    let predicate = Predicate::eq("ticker", "AAPL");
+    let err = deserialize_where::<DailyBlotterData>(file_path.clone(), &predicate)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::UnknownField { .. }));
+    let predicate = Predicate::eq("qty", "lots");
+    let err = deserialize_where::<DailyBlotterData>(file_path.clone(), &predicate)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidKey { .. }));
+
+    // Clean up test files
+    tokio::fs::remove_file(&*file_path).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
+
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use std::sync::Arc;
+use yohsin::index::{build_index, index_path, lookup_by_key, KeyIndex};
+use yohsin::order_struct::DailyBlotterData;
+use yohsin::serialize::{append_to_file, serialize_to_file_with_options, SerializeOptions};
+use yohsin::Error;
+
+#[tokio::test]
+async fn test_lookup_by_key() -> Result<(), Box<dyn std::error::Error>> {
+    let original_data = DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?;
+
+    let file_path = Arc::new("test_key_index_dump.bin".to_string());
+    let journal_file = Arc::new("test_key_index_journal.txt".to_string());
+    let options = SerializeOptions {
+        chunk_records: 16,
+        index_fields: DailyBlotterData::KEY_FIELDS.to_vec(),
+        ..SerializeOptions::default()
+    };
+    serialize_to_file_with_options(
+        Arc::clone(&original_data),
+        file_path.clone(),
+        journal_file.clone(),
+        &options,
+    )
+    .await?;
+
+    // Every record is found by each of its keys
+    for record in original_data.iter().step_by(7) {
+        let expected: Vec<DailyBlotterData> = original_data
+            .iter()
+            .filter(|other| other.orderid == record.orderid)
+            .cloned()
+            .collect();
+        let found =
+            lookup_by_key::<DailyBlotterData>(&file_path, "orderid", &record.orderid).await?;
+        assert_eq!(expected, found);
+
+        let found =
+            lookup_by_key::<DailyBlotterData>(&file_path, "clorderid", &record.clorderid).await?;
+        assert!(found.contains(record));
+        assert!(found
+            .iter()
+            .all(|other| other.clorderid == record.clorderid));
+    }
+    let found = lookup_by_key::<DailyBlotterData>(&file_path, "orderid", "no such order").await?;
+    assert!(found.is_empty());
+
+    // An index kept open serves every lookup without being read again
+    let index = KeyIndex::open::<DailyBlotterData>(&file_path, "orderid").await?;
+    assert_eq!(index.len(), original_data.len());
+    for record in &original_data[..20] {
+        let found = index
+            .lookup::<DailyBlotterData>(&file_path, &record.orderid)
+            .await?;
+        assert!(found.contains(record));
+    }
+
+    // Fields that are not indexed, or do not exist, are rejected
+    let err = lookup_by_key::<DailyBlotterData>(&file_path, "trader_name", "x")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Io(_)));
+    let err = lookup_by_key::<DailyBlotterData>(&file_path, "no_such_field", "x")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::UnknownField { .. }));
+
+    // Scalar fields take their key as in a CSV file
+    build_index::<DailyBlotterData>(&file_path, "id").await?;
+    let record = &original_data[42];
+    let found = lookup_by_key::<DailyBlotterData>(&file_path, "id", &record.id.to_string()).await?;
+    assert!(found.contains(record));
+    let err = lookup_by_key::<DailyBlotterData>(&file_path, "id", "forty-two")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidKey { .. }));
+
+    // Appending leaves the index stale until it is rebuilt
+    append_to_file(Arc::from(&original_data[..10]), file_path.clone()).await?;
+    let key = &original_data[3].orderid;
+    let err = lookup_by_key::<DailyBlotterData>(&file_path, "orderid", key)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::StaleIndex { .. }));
+    let err = index
+        .lookup::<DailyBlotterData>(&file_path, key)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::StaleIndex { .. }));
+    build_index::<DailyBlotterData>(&file_path, "orderid").await?;
+    let found = lookup_by_key::<DailyBlotterData>(&file_path, "orderid", key).await?;
+    assert!(found.len() >= 2);
+    assert!(found.iter().all(|record| &record.orderid == key));
+
+    // Clean up test files
+    for field in ["orderid", "clorderid", "id"] {
+        tokio::fs::remove_file(index_path(&file_path, field)).await?;
+    }
+    tokio::fs::remove_file(&*file_path).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
+
+    Ok(())
+}
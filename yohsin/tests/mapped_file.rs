@@ -0,0 +1,95 @@
+use std::sync::Arc;
+use yohsin::chunk::ChunkTable;
+use yohsin::header::FileHeader;
+use yohsin::mapped::MappedFile;
+use yohsin::order_struct::{DailyBlotterData, DailyBlotterDataView};
+use yohsin::serialize::{serialize_to_file_with_options, SerializeOptions};
+use yohsin::{Error, Record};
+
+#[tokio::test]
+async fn test_mapped_views() -> Result<(), Box<dyn std::error::Error>> {
+    let original_data = DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?;
+
+    let file_path = Arc::new("test_mapped_dump.bin".to_string());
+    let journal_file = Arc::new("test_mapped_journal.txt".to_string());
+    let options = SerializeOptions {
+        chunk_records: 16,
+        ..SerializeOptions::default()
+    };
+    serialize_to_file_with_options(
+        Arc::clone(&original_data),
+        file_path.clone(),
+        journal_file.clone(),
+        &options,
+    )
+    .await?;
+
+    // SAFETY: nothing writes to the file until it is dropped
+    let mapped = unsafe { MappedFile::<DailyBlotterData>::open(&file_path)? };
+    assert_eq!(mapped.len(), original_data.len());
+
+    // Random access borrows the fields straight from the mapping
+    let view: DailyBlotterDataView = mapped.get(37)?;
+    let symbol: &str = view.symbol;
+    assert_eq!(symbol, original_data[37].symbol);
+    assert_eq!(view.orderid, original_data[37].orderid);
+    assert_eq!(DailyBlotterData::from_view(view), original_data[37]);
+
+    let retrieved: Vec<DailyBlotterData> = mapped
+        .range(20..50)?
+        .map(|view| view.map(DailyBlotterData::from_view))
+        .collect::<Result<_, _>>()?;
+    assert_eq!(original_data[20..50], *retrieved);
+    let all: Vec<DailyBlotterData> = mapped
+        .iter()
+        .map(|view| view.map(DailyBlotterData::from_view))
+        .collect::<Result<_, _>>()?;
+    assert_eq!(original_data[..], *all);
+    let chunk = mapped.chunks().nth(2).unwrap()?;
+    assert_eq!(chunk.len(), 16);
+    assert_eq!(
+        DailyBlotterData::from_view(chunk.get(5)?),
+        original_data[37]
+    );
+    assert!(matches!(chunk.get(16), Err(Error::RangeOutOfBounds { .. })));
+
+    assert!(matches!(
+        mapped.get(original_data.len()),
+        Err(Error::RangeOutOfBounds { .. })
+    ));
+    assert!(matches!(
+        mapped.range(10..original_data.len() + 1),
+        Err(Error::RangeOutOfBounds { .. })
+    ));
+    drop(mapped);
+
+    // A damaged chunk only fails the records it holds
+    let mut bytes = tokio::fs::read(&*file_path).await?;
+    let header = FileHeader::decode(&bytes)?;
+    let header_len = header.encode().len();
+    let table = ChunkTable::decode(
+        &bytes[header.chunk_table_offset as usize..],
+        &bytes[..header_len],
+    )?;
+    let entry = table.entries[2];
+    bytes[(entry.offset + entry.len - 1) as usize] ^= 0x01;
+    tokio::fs::write(&*file_path, &bytes).await?;
+
+    // SAFETY: nothing writes to the file until it is dropped
+    let mapped = unsafe { MappedFile::<DailyBlotterData>::open(&file_path)? };
+    assert!(matches!(
+        mapped.get(40),
+        Err(Error::ChecksumMismatch { chunk: 2, .. })
+    ));
+    assert_eq!(
+        DailyBlotterData::from_view(mapped.get(50)?),
+        original_data[50]
+    );
+    drop(mapped);
+
+    // Clean up test files
+    tokio::fs::remove_file(&*file_path).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
+
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+use yohsin::decimal::Price;
+use yohsin::order_struct::DailyBlotterData;
+use yohsin::project::deserialize_projected;
+use yohsin::serialize::{serialize_to_file_with_options, SerializeOptions};
+use yohsin::{Error, Record};
+
+/// A few fields of `DailyBlotterData`, not in their order
+#[derive(Debug, PartialEq, Clone, Record)]
+struct Fill {
+    priceexec: Price,
+    orderid: String,
+    qtyexec: i64,
+    isblotter: bool,
+    trader_name: String,
+}
+
+#[derive(Debug, PartialEq, Clone, Record)]
+struct WrongType {
+    qtyexec: f64,
+}
+
+#[derive(Debug, PartialEq, Clone, Record)]
+struct UnknownField {
+    venue: String,
+}
+
+#[tokio::test]
+async fn test_deserialize_projected() -> Result<(), Box<dyn std::error::Error>> {
+    let original_data = DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?;
+
+    let file_path = Arc::new("test_projection_dump.bin".to_string());
+    let journal_file = Arc::new("test_projection_journal.txt".to_string());
+    let options = SerializeOptions {
+        chunk_records: 16,
+        ..SerializeOptions::default()
+    };
+    serialize_to_file_with_options(
+        Arc::clone(&original_data),
+        file_path.clone(),
+        journal_file.clone(),
+        &options,
+    )
+    .await?;
+
+    let fills = deserialize_projected::<DailyBlotterData, Fill>(file_path.clone()).await?;
+    let expected: Vec<Fill> = original_data
+        .iter()
+        .map(|record| Fill {
+            priceexec: record.priceexec,
+            orderid: record.orderid.clone(),
+            qtyexec: record.qtyexec,
+            isblotter: record.isblotter,
+            trader_name: record.trader_name.clone(),
+        })
+        .collect();
+    assert_eq!(expected, *fills);
+
+    // Fields must exist in the file with the same type
+    let err = deserialize_projected::<DailyBlotterData, WrongType>(file_path.clone())
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::UnsupportedFieldType {
+            field: "qtyexec",
+            ..
+        }
+    ));
+    let err = deserialize_projected::<DailyBlotterData, UnknownField>(file_path.clone())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::UnknownField { .. }));
+
+    // Clean up test files
+    tokio::fs::remove_file(&*file_path).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
+
+    Ok(())
+}
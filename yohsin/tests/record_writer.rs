@@ -0,0 +1,145 @@
+mod common;
+
+use common::{normalized, XorShift};
+use std::sync::Arc;
+use yohsin::failpoint;
+use yohsin::order_struct::DailyBlotterData;
+use yohsin::serialize::{deserialize_from_file, serialize_to_file_with_options, SerializeOptions};
+use yohsin::writer::RecordWriter;
+
+fn options() -> SerializeOptions {
+    SerializeOptions {
+        chunk_records: 8,
+        ..SerializeOptions::default()
+    }
+}
+
+#[tokio::test]
+async fn test_writer_matches_serialize() -> Result<(), Box<dyn std::error::Error>> {
+    let original_data = DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?;
+
+    let reference_path = Arc::new("test_writer_reference_dump.bin".to_string());
+    let reference_journal = Arc::new("test_writer_reference_journal.txt".to_string());
+    serialize_to_file_with_options(
+        Arc::clone(&original_data),
+        reference_path.clone(),
+        reference_journal.clone(),
+        &options(),
+    )
+    .await?;
+
+    // Feed the writer one record, then an iterator, then a channel
+    let file_path = Arc::new("test_writer_dump.bin".to_string());
+    let journal_file = Arc::new("test_writer_journal.txt".to_string());
+    let mut writer =
+        RecordWriter::create_with_options(file_path.clone(), journal_file.clone(), &options())
+            .await?;
+    writer.push(original_data[0].clone()).await?;
+    writer.extend(original_data[1..50].iter().cloned()).await?;
+
+    let (sender, receiver) = tokio::sync::mpsc::channel(4);
+    let rest = Arc::clone(&original_data);
+    let producer = tokio::spawn(async move {
+        for record in rest[50..].iter().cloned() {
+            sender.send(record).await.unwrap();
+        }
+    });
+    writer.consume(receiver).await?;
+    producer.await?;
+    assert_eq!(writer.record_count(), original_data.len() as u64);
+    writer.finish().await?;
+
+    let retrieved_data = deserialize_from_file::<DailyBlotterData>(file_path.clone()).await?;
+    assert_eq!(*original_data, *retrieved_data);
+    assert!(normalized(&file_path).await? == normalized(&reference_path).await?);
+    assert!(tokio::fs::read(&*journal_file).await?.is_empty());
+
+    // Clean up test files
+    for path in [
+        &file_path,
+        &journal_file,
+        &reference_path,
+        &reference_journal,
+    ] {
+        tokio::fs::remove_file(&**path).await?;
+    }
+
+    Ok(())
+}
+
+/// Writes `data` with a writer, skipping the records a previous run made
+/// durable.
+async fn write_all(
+    data: &[DailyBlotterData],
+    file_path: &Arc<String>,
+    journal_file: &Arc<String>,
+) -> yohsin::Result<()> {
+    let mut writer =
+        RecordWriter::create_with_options(file_path.clone(), journal_file.clone(), &options())
+            .await?;
+    let resumed = writer.resumed_records() as usize;
+    writer.extend(data[resumed..].iter().cloned()).await?;
+    writer.finish().await
+}
+
+#[tokio::test]
+async fn test_writer_crash_and_resume() -> Result<(), Box<dyn std::error::Error>> {
+    let original_data = DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?;
+
+    // Reference output of an uninterrupted run
+    let reference_path = Arc::new("test_writer_crash_reference_dump.bin".to_string());
+    let reference_journal = Arc::new("test_writer_crash_reference_journal.txt".to_string());
+    write_all(&original_data, &reference_path, &reference_journal).await?;
+    let expected = normalized(&reference_path).await?;
+
+    let file_path = Arc::new("test_writer_crash_dump.bin".to_string());
+    let journal_file = Arc::new("test_writer_crash_journal.txt".to_string());
+    for seed in 1..=40u64 {
+        let mut rng = XorShift(seed);
+        let _ = tokio::fs::remove_file(&*file_path).await;
+        let _ = tokio::fs::remove_file(&*journal_file).await;
+
+        // Crash at a random checkpoint until a run gets through
+        let mut crashes = 0;
+        loop {
+            failpoint::arm(&file_path, rng.next(48) as usize);
+            match write_all(&original_data, &file_path, &journal_file).await {
+                Ok(()) => break,
+                Err(err) => assert!(
+                    failpoint::is_injected(&err),
+                    "Seed {}: unexpected error: {}",
+                    seed,
+                    err
+                ),
+            }
+            crashes += 1;
+            assert!(crashes < 100, "Seed {}: writing never completes", seed);
+
+            // A crashed file is either rejected or already complete, never wrong
+            if let Ok(data) = deserialize_from_file::<DailyBlotterData>(file_path.clone()).await {
+                assert_eq!(*original_data, *data, "Seed {}", seed);
+            }
+        }
+        failpoint::disarm(&file_path);
+
+        let retrieved_data = deserialize_from_file::<DailyBlotterData>(file_path.clone()).await?;
+        assert_eq!(*original_data, *retrieved_data, "Seed {}", seed);
+        assert!(
+            normalized(&file_path).await? == expected,
+            "Seed {}: resumed file differs from an uninterrupted run",
+            seed
+        );
+    }
+
+    // Clean up test files
+    for path in [
+        &file_path,
+        &journal_file,
+        &reference_path,
+        &reference_journal,
+    ] {
+        tokio::fs::remove_file(&**path).await?;
+    }
+
+    Ok(())
+}
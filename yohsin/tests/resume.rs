@@ -0,0 +1,106 @@
+use std::sync::Arc;
+use yohsin::chunk::{ChunkTable, Layout};
+use yohsin::compression::Compression;
+use yohsin::header::FileHeader;
+use yohsin::journal::{layout_fingerprint, Journal};
+use yohsin::order_struct::DailyBlotterData;
+use yohsin::serialize::{deserialize_from_file, serialize_to_file_with_options, SerializeOptions};
+
+#[tokio::test]
+async fn test_resume_interrupted_serialization() -> Result<(), Box<dyn std::error::Error>> {
+    let original_data = DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?;
+
+    let file_path = Arc::new("test_resume_dump.bin".to_string());
+    let journal_file = Arc::new("test_resume_journal.txt".to_string());
+    let options = SerializeOptions {
+        chunk_records: 10,
+        threads: 4,
+        ..SerializeOptions::default()
+    };
+
+    // Reference output of an uninterrupted run
+    serialize_to_file_with_options(
+        Arc::clone(&original_data),
+        file_path.clone(),
+        journal_file.clone(),
+        &options,
+    )
+    .await?;
+    let complete = tokio::fs::read(&*file_path).await?;
+    assert!(tokio::fs::read(&*journal_file).await?.is_empty());
+
+    // Rebuild the state left by a run interrupted after writing some chunks:
+    // no header, no chunk table, and a journal listing the durable chunks.
+    // Chunk 2 is journaled but its bytes were lost, chunk 3 was never written.
+    let header = FileHeader::decode(&complete)?;
+    let header_len = header.encode().len();
+    let table = ChunkTable::decode(
+        &complete[header.chunk_table_offset as usize..],
+        &complete[..header_len],
+    )?;
+    let mut interrupted = complete.clone();
+    interrupted[..header_len].fill(0);
+    interrupted[header.chunk_table_offset as usize..].fill(0);
+    for idx in [2, 3, 7, 14] {
+        let entry = table.entries[idx];
+        interrupted[entry.offset as usize..(entry.offset + entry.len) as usize].fill(0);
+    }
+    tokio::fs::write(&*file_path, &interrupted).await?;
+
+    let layout = layout_fingerprint(&header, &table);
+    let mut journal = Journal::begin(&journal_file, layout, header.created_at).await?;
+    journal.complete(&[0, 1, 2, 4, 5, 6, 8, 9, 10]).await?;
+    drop(journal);
+
+    // The interrupted file is not readable
+    assert!(deserialize_from_file::<DailyBlotterData>(file_path.clone())
+        .await
+        .is_err());
+
+    // Resuming produces exactly the file of the uninterrupted run
+    serialize_to_file_with_options(
+        Arc::clone(&original_data),
+        file_path.clone(),
+        journal_file.clone(),
+        &options,
+    )
+    .await?;
+    assert!(tokio::fs::read(&*file_path).await? == complete);
+    assert!(tokio::fs::read(&*journal_file).await?.is_empty());
+
+    let retrieved_data = deserialize_from_file::<DailyBlotterData>(file_path.clone()).await?;
+    assert_eq!(*original_data, *retrieved_data);
+
+    // Clean up test files
+    tokio::fs::remove_file(&*file_path).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_incomplete_stream_line_is_not_resumed() -> Result<(), Box<dyn std::error::Error>> {
+    let journal_file = "test_resume_stream_journal.txt";
+    let entry = "entry 100 50 0 8 1a2b3c4d\n";
+
+    tokio::fs::write(
+        journal_file,
+        format!("stream 2a 8 1700000000 1 2\n{}", entry),
+    )
+    .await?;
+    let state = Journal::load_stream(journal_file).await.unwrap();
+    assert_eq!(state.layout, Layout::Columns);
+    assert_eq!(state.compression, Compression::Zstd);
+    assert_eq!(state.entries.len(), 1);
+
+    // A line missing its layout or compression is not read as the defaults
+    for stream in ["stream 2a 8 1700000000 1", "stream 2a 8 1700000000"] {
+        tokio::fs::write(journal_file, format!("{}\n{}", stream, entry)).await?;
+        assert_eq!(Journal::load_stream(journal_file).await, None, "{}", stream);
+    }
+
+    // Clean up test files
+    tokio::fs::remove_file(journal_file).await?;
+
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use yohsin::failpoint;
+use yohsin::header::FileHeader;
+use yohsin::order_struct::DailyBlotterData;
+use yohsin::segment::{SegmentOptions, SegmentedStore};
+use yohsin::{Error, Record};
+
+fn options() -> SegmentOptions {
+    SegmentOptions {
+        max_records: 40,
+        chunk_records: 16,
+        ..SegmentOptions::default()
+    }
+}
+
+#[tokio::test]
+async fn test_segmented_store() -> Result<(), Box<dyn std::error::Error>> {
+    let original_data = DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?;
+    let dir = "test_segmented_store";
+    let archive_dir = "test_segmented_store_archive";
+    let _ = tokio::fs::remove_dir_all(dir).await;
+
+    let mut store = SegmentedStore::<DailyBlotterData>::open(dir, options()).await?;
+    store.extend(original_data[..100].iter().cloned()).await?;
+    store.close().await?;
+
+    // Reopening continues after the records already stored
+    let mut store = SegmentedStore::<DailyBlotterData>::open(dir, options()).await?;
+    assert_eq!(store.next_record(), 100);
+    store.extend(original_data[100..].iter().cloned()).await?;
+    store.roll().await?;
+
+    let ranges: Vec<_> = store.segments().iter().map(|s| s.records()).collect();
+    assert_eq!(ranges, [0..40, 40..80, 80..100, 100..140, 140..150]);
+    let retrieved = store.read_range(30..125).await?;
+    assert_eq!(original_data[30..125], *retrieved);
+    assert_eq!(original_data[..], *store.read_range(0..150).await?);
+    assert!(matches!(
+        store.read_range(140..151).await,
+        Err(Error::RangeOutOfBounds { .. })
+    ));
+
+    // Old segments go away without renumbering the others
+    let first = store.segments()[0].id;
+    store.delete_segment(first).await?;
+    let second = store.segments()[0].id;
+    let archived = store.archive_segment(second, archive_dir).await?;
+    assert!(tokio::fs::metadata(&archived).await.is_ok());
+    assert!(matches!(
+        store.read_range(70..90).await,
+        Err(Error::MissingRecords { start: 70, end: 80 })
+    ));
+    assert_eq!(original_data[80..150], *store.read_range(80..150).await?);
+    drop(store);
+
+    let store = SegmentedStore::<DailyBlotterData>::open(dir, options()).await?;
+    assert_eq!(store.segments().len(), 3);
+    assert_eq!(original_data[90..110], *store.read_range(90..110).await?);
+
+    // Clean up test files
+    tokio::fs::remove_dir_all(dir).await?;
+    tokio::fs::remove_dir_all(archive_dir).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_segmented_store_resumes_active_segment() -> Result<(), Box<dyn std::error::Error>> {
+    let original_data = DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?;
+    let dir = "test_segmented_store_resume";
+    let _ = tokio::fs::remove_dir_all(dir).await;
+
+    let mut store = SegmentedStore::<DailyBlotterData>::open(dir, options()).await?;
+    store.extend(original_data[..60].iter().cloned()).await?;
+
+    // Crash while writing the second chunk of the second segment
+    let active = store.segment_path(1);
+    failpoint::arm(&active, 0);
+    let err = store
+        .extend(original_data[60..].iter().cloned())
+        .await
+        .unwrap_err();
+    assert!(failpoint::is_injected(&err));
+    failpoint::disarm(&active);
+    drop(store);
+
+    let mut store = SegmentedStore::<DailyBlotterData>::open(dir, options()).await?;
+    assert_eq!(store.resumed_records(), 16);
+    let next = store.next_record() as usize;
+    assert_eq!(next, 56);
+    store.extend(original_data[next..].iter().cloned()).await?;
+    store.close().await?;
+
+    let store = SegmentedStore::<DailyBlotterData>::open(dir, options()).await?;
+    assert_eq!(original_data[..], *store.read_range(0..150).await?);
+
+    // Clean up test files
+    tokio::fs::remove_dir_all(dir).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_segmented_store_rolls_by_size() -> Result<(), Box<dyn std::error::Error>> {
+    let original_data = DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?;
+    let dir = "test_segmented_store_size";
+    let _ = tokio::fs::remove_dir_all(dir).await;
+
+    // Records still pending in the writer count towards the size, so
+    // segments roll well before their first chunk is written
+    let header_len = FileHeader::new::<DailyBlotterData>(0, 0).encode().len() as u64;
+    let min_len = DailyBlotterData::SCHEMA.fixed_len() as u64;
+    let options = SegmentOptions {
+        max_records: u64::MAX,
+        max_bytes: header_len + 20 * min_len,
+        chunk_records: 64,
+        ..SegmentOptions::default()
+    };
+    let mut store = SegmentedStore::<DailyBlotterData>::open(dir, options).await?;
+    store.extend(original_data[..50].iter().cloned()).await?;
+    store.roll().await?;
+
+    let ranges: Vec<_> = store.segments().iter().map(|s| s.records()).collect();
+    assert_eq!(ranges, [0..20, 20..40, 40..50]);
+    assert_eq!(original_data[..50], *store.read_range(0..50).await?);
+
+    // Clean up test files
+    tokio::fs::remove_dir_all(dir).await?;
+
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use futures::TryStreamExt;
+use std::sync::Arc;
+use yohsin::chunk::ChunkTable;
+use yohsin::header::FileHeader;
+use yohsin::order_struct::DailyBlotterData;
+use yohsin::serialize::{
+    iter_records, iter_records_from, serialize_to_file_with_options, stream_records,
+    stream_records_from, SerializeOptions,
+};
+use yohsin::Error;
+
+#[tokio::test]
+async fn test_stream_records() -> Result<(), Box<dyn std::error::Error>> {
+    let original_data = DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?;
+
+    let file_path = Arc::new("test_stream_dump.bin".to_string());
+    let journal_file = Arc::new("test_stream_journal.txt".to_string());
+    let options = SerializeOptions {
+        chunk_records: 16,
+        ..SerializeOptions::default()
+    };
+    serialize_to_file_with_options(
+        Arc::clone(&original_data),
+        file_path.clone(),
+        journal_file.clone(),
+        &options,
+    )
+    .await?;
+
+    let streamed: Vec<DailyBlotterData> = stream_records(file_path.clone()).try_collect().await?;
+    assert_eq!(original_data[..], *streamed);
+    let iterated = iter_records::<DailyBlotterData>(&file_path)?.collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(original_data[..], *iterated);
+
+    // Starting in the middle of a chunk
+    let streamed: Vec<DailyBlotterData> = stream_records_from(file_path.clone(), 45)
+        .try_collect()
+        .await?;
+    assert_eq!(original_data[45..], *streamed);
+    let iterated =
+        iter_records_from::<DailyBlotterData>(&file_path, 45)?.collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(original_data[45..], *iterated);
+
+    let len = original_data.len();
+    assert_eq!(
+        iter_records_from::<DailyBlotterData>(&file_path, len)?.count(),
+        0
+    );
+    assert!(matches!(
+        iter_records_from::<DailyBlotterData>(&file_path, len + 1),
+        Err(Error::RangeOutOfBounds { .. })
+    ));
+    let err = stream_records_from::<DailyBlotterData>(file_path.clone(), len + 1)
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::RangeOutOfBounds { .. }));
+
+    // A damaged chunk ends the stream once the records before it are read
+    let mut bytes = tokio::fs::read(&*file_path).await?;
+    let header = FileHeader::decode(&bytes)?;
+    let header_len = header.encode().len();
+    let table = ChunkTable::decode(
+        &bytes[header.chunk_table_offset as usize..],
+        &bytes[..header_len],
+    )?;
+    let entry = table.entries[2];
+    bytes[(entry.offset + entry.len - 1) as usize] ^= 0x01;
+    tokio::fs::write(&*file_path, &bytes).await?;
+
+    let results: Vec<_> = iter_records::<DailyBlotterData>(&file_path)?.collect();
+    assert_eq!(results.len(), 33);
+    assert!(results[..32].iter().all(|r| r.is_ok()));
+    assert!(matches!(
+        results[32],
+        Err(Error::ChecksumMismatch { chunk: 2, .. })
+    ));
+    let mut stream = std::pin::pin!(stream_records::<DailyBlotterData>(file_path.clone()));
+    let mut read = 0;
+    let err = loop {
+        match stream.try_next().await {
+            Ok(Some(_)) => read += 1,
+            Ok(None) => panic!("The damaged chunk was not detected"),
+            Err(e) => break e,
+        }
+    };
+    assert_eq!(read, 32);
+    assert!(matches!(err, Error::ChecksumMismatch { chunk: 2, .. }));
+
+    // Clean up test files
+    tokio::fs::remove_file(&*file_path).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
+
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use std::sync::Arc;
+use yohsin::chunk::ChunkTable;
+use yohsin::header::FileHeader;
+use yohsin::order_struct::DailyBlotterData;
+use yohsin::serialize::{serialize_to_file_with_options, SerializeOptions};
+use yohsin::time_index::{build_time_index, deserialize_time_range, time_index_path};
+use yohsin::Error;
+
+#[tokio::test]
+async fn test_deserialize_time_range() -> Result<(), Box<dyn std::error::Error>> {
+    let mut original_data =
+        DailyBlotterData::load_from_file("../data_baker/data/data-file.csv")?.to_vec();
+
+    // The fixture has a single order time: spread the orders over 25 minutes
+    for (idx, record) in original_data.iter_mut().enumerate() {
+        record.ordertime += 10 * idx as i64;
+    }
+
+    let file_path = Arc::new("test_time_range_dump.bin".to_string());
+    let journal_file = Arc::new("test_time_range_journal.txt".to_string());
+    let options = SerializeOptions {
+        chunk_records: 10,
+        time_index_fields: vec![DailyBlotterData::TIME_FIELD],
+        ..SerializeOptions::default()
+    };
+    serialize_to_file_with_options(
+        Arc::from(original_data.clone()),
+        file_path.clone(),
+        journal_file.clone(),
+        &options,
+    )
+    .await?;
+
+    let in_window = |from: i64, to: i64| -> Vec<DailyBlotterData> {
+        original_data
+            .iter()
+            .filter(|record| from <= record.ordertime && record.ordertime < to)
+            .cloned()
+            .collect()
+    };
+    let first = original_data[0].ordertime;
+    let last = original_data[original_data.len() - 1].ordertime;
+    let windows = [
+        (first, last + 1),
+        (original_data[35].ordertime, original_data[72].ordertime),
+        (
+            original_data[100].ordertime,
+            original_data[100].ordertime + 1,
+        ),
+        (last + 1, last + 100),
+        (first - 100, first),
+    ];
+    for (from, to) in windows {
+        let found = deserialize_time_range::<DailyBlotterData>(
+            file_path.clone(),
+            DailyBlotterData::TIME_FIELD,
+            from,
+            to,
+        )
+        .await?;
+        assert_eq!(in_window(from, to), *found);
+    }
+
+    // Chunks outside of the window are not read: damage to chunk 0 goes
+    // unnoticed by a query on the last records
+    let complete = tokio::fs::read(&*file_path).await?;
+    let header = FileHeader::decode(&complete)?;
+    let header_len = header.encode().len();
+    let table_offset = header.chunk_table_offset as usize;
+    let table = ChunkTable::decode(&complete[table_offset..], &complete[..header_len])?;
+    let entry = table.entries[0];
+    let mut corrupted = complete.clone();
+    corrupted[(entry.offset + entry.len / 2) as usize] ^= 0x10;
+    tokio::fs::write(&*file_path, &corrupted).await?;
+
+    let from = original_data[140].ordertime;
+    let found =
+        deserialize_time_range::<DailyBlotterData>(file_path.clone(), "ordertime", from, last + 1)
+            .await?;
+    assert_eq!(in_window(from, last + 1), *found);
+    let err =
+        deserialize_time_range::<DailyBlotterData>(file_path.clone(), "ordertime", first, last + 1)
+            .await
+            .unwrap_err();
+    assert!(matches!(err, Error::ChecksumMismatch { chunk: 0, .. }));
+    tokio::fs::write(&*file_path, &complete).await?;
+
+    // Only integer fields can be indexed, and indexes must match their file
+    let err = build_time_index::<DailyBlotterData>(&file_path, "symbol")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::UnsupportedFieldType { .. }));
+    build_time_index::<DailyBlotterData>(&file_path, "orderdate").await?;
+    serialize_to_file_with_options(
+        Arc::from(&original_data[..50]),
+        file_path.clone(),
+        journal_file.clone(),
+        &options,
+    )
+    .await?;
+    let err =
+        deserialize_time_range::<DailyBlotterData>(file_path.clone(), "orderdate", first, last)
+            .await
+            .unwrap_err();
+    assert!(matches!(err, Error::StaleIndex { .. }));
+
+    // Clean up test files
+    for field in ["ordertime", "orderdate"] {
+        tokio::fs::remove_file(time_index_path(&file_path, field)).await?;
+    }
+    tokio::fs::remove_file(&*file_path).await?;
+    tokio::fs::remove_file(&*journal_file).await?;
+
+    Ok(())
+}
@@ -0,0 +1,129 @@
+//! `#[derive(Record)]` for the `yohsin` storage engine.
+//!
+//! Fields are encoded, described in the schema and mapped to CSV columns in
+//! declaration order. Every field type must implement
+//! `yohsin::record::FieldValue`.
+
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Fields};
+
+#[proc_macro_derive(Record)]
+pub fn derive_record(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "Record can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "Record can only be derived for structs",
+            ))
+        }
+    };
+
+    // The borrowed view would need the same parameters, and no record uses any
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "Record cannot be derived for generic structs",
+        ));
+    }
+
+    let name = &input.ident;
+    let name_str = name.to_string();
+    let vis = &input.vis;
+    let view = format_ident!("{}View", name);
+    let view_doc = format!("Borrowed view of a [`{}`], see `Record::view`.", name);
+
+    let idents: Vec<_> = fields.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+    let names: Vec<_> = idents.iter().map(|i| i.to_string()).collect();
+    let types: Vec<_> = fields.iter().map(|f| &f.ty).collect();
+    let indices: Vec<_> = (0..fields.len()).collect();
+    let count = fields.len();
+
+    Ok(quote! {
+        #[doc = #view_doc]
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        #vis struct #view<'a> {
+            #(pub #idents: <#types as ::yohsin::record::FieldValue>::Ref<'a>,)*
+        }
+
+        impl ::yohsin::Record for #name {
+            const SCHEMA: &'static ::yohsin::record::Schema = &::yohsin::record::Schema {
+                name: #name_str,
+                fields: &[
+                    #(::yohsin::record::Field {
+                        name: #names,
+                        ty: <#types as ::yohsin::record::FieldValue>::TYPE,
+                    },)*
+                ],
+            };
+
+            fn encode(&self, buf: &mut ::std::vec::Vec<u8>) {
+                let mut w = ::yohsin::codec::RowWriter::new(buf, Self::SCHEMA.fixed_len());
+                #(::yohsin::record::FieldValue::put(&self.#idents, &mut w);)*
+            }
+
+            fn decode(bytes: &[u8]) -> ::yohsin::Result<Self> {
+                let mut r = ::yohsin::codec::RowReader::new(bytes, Self::SCHEMA.fixed_len())?;
+                ::std::result::Result::Ok(#name {
+                    #(#idents: ::yohsin::record::FieldValue::get(&mut r)?,)*
+                })
+            }
+
+            type View<'a> = #view<'a>;
+
+            fn view(bytes: &[u8]) -> ::yohsin::Result<#view<'_>> {
+                let mut r = ::yohsin::codec::RowReader::new(bytes, Self::SCHEMA.fixed_len())?;
+                ::std::result::Result::Ok(#view {
+                    #(#idents: <#types as ::yohsin::record::FieldValue>::get_ref(&mut r)?,)*
+                })
+            }
+
+            fn from_view(view: #view<'_>) -> Self {
+                #name {
+                    #(#idents: <#types as ::yohsin::record::FieldValue>::from_ref(view.#idents),)*
+                }
+            }
+
+            fn from_csv_fields(fields: &[&str]) -> ::yohsin::Result<Self> {
+                if fields.len() != #count {
+                    return ::std::result::Result::Err(::yohsin::Error::CsvFieldCount {
+                        line: 0,
+                        expected: #count,
+                        found: fields.len(),
+                    });
+                }
+                ::std::result::Result::Ok(#name {
+                    #(#idents: <#types as ::yohsin::record::FieldValue>::parse(fields[#indices])
+                        .map_err(|reason| ::yohsin::Error::CsvParse {
+                            line: 0,
+                            column: #indices + 1,
+                            field: #names,
+                            value: ::std::string::ToString::to_string(fields[#indices]),
+                            reason,
+                        })?,)*
+                })
+            }
+
+            fn to_csv_fields(&self) -> ::std::vec::Vec<::std::string::String> {
+                ::std::vec![#(::yohsin::record::FieldValue::format(&self.#idents)),*]
+            }
+        }
+    })
+}